@@ -0,0 +1,157 @@
+//! The columns of pg_stat_activity that the /rpcz of YSQL doesn't have: with a SQL connection per node,
+//! the pid, user, wait event and query id of the backends are read and added to the rows of their
+//! sessions, which are found by client address and port and backend start time.
+use crate::error::Error;
+use crate::locks;
+use crate::rpcz::AllConnections::Connections;
+use crate::snapshot::ClusterSnapshot;
+use crate::ysql::{ClientPool, Login};
+use chrono::DateTime;
+use postgres::Client;
+use serde_derive::{Deserialize, Serialize};
+
+/// The backends with a client address. The backend start time is read as text in UTC, as /rpcz shows it,
+/// to have its exact microseconds, which extract(epoch) as a float can be one off. query_id is read from
+/// the row as json, as versions before PostgreSQL 14 don't have it.
+const ACTIVITY: &str = "select host(a.client_addr), a.client_port,
+    to_char(a.backend_start at time zone 'UTC', 'YYYY-MM-DD HH24:MI:SS.US') || '+00', a.pid,
+    coalesce(a.usename::text, ''), coalesce(a.wait_event_type, ''), coalesce(a.wait_event, ''), (to_jsonb(a) ->> 'query_id')::int8
+from pg_stat_activity a where a.client_addr is not null and a.backend_start is not null";
+
+/// What pg_stat_activity tells of the backend of a YSQL session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Activity {
+    /// the client address and port, as in the session list.
+    pub client: String,
+    /// the backend start time, in microseconds since the epoch.
+    pub backend_start: i64,
+    pub pid: i32,
+    pub user: String,
+    pub wait_event_type: String,
+    pub wait_event: String,
+    pub query_id: Option<i64>,
+}
+
+impl Activity {
+    /// The wait event with its type, such as Lock:transactionid, or empty when the backend doesn't wait.
+    pub fn wait(&self) -> String {
+        wait_event(&self.wait_event_type, &self.wait_event)
+    }
+}
+
+/// A wait event type and event as type:event, or the one of them that isn't empty.
+pub fn wait_event(event_type: &str, event: &str) -> String {
+    match (event_type, event) {
+        ("", "") => String::new(),
+        ("", event) | (event, "") => event.to_string(),
+        (event_type, event) => format!("{}:{}", event_type, event),
+    }
+}
+
+/// A time as /rpcz shows it, such as 2022-03-27 15:55:22.587029+00, in microseconds since the epoch.
+pub fn micros(time: &str) -> Option<i64> {
+    DateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.f%#z").ok().map(|time| time.timestamp_micros())
+}
+
+/// The backends of a server.
+pub fn read_activity(client: &mut Client) -> Result<Vec<Activity>, Error> {
+    Ok(client
+        .query(ACTIVITY, &[])?
+        .iter()
+        .map(|row| {
+            let address: String = row.get(0);
+            let port: i32 = row.get(1);
+            let backend_start: String = row.get(2);
+            Activity {
+                client: format!("{}:{}", address, port),
+                backend_start: micros(&backend_start).unwrap_or_default(),
+                pid: row.get(3),
+                user: row.get(4),
+                wait_event_type: row.get(5),
+                wait_event: row.get(6),
+                query_id: row.get(7),
+            }
+        })
+        .collect())
+}
+
+/// Keeps a connection to every node, to read pg_stat_activity at every refresh.
+pub struct ActivityCollector {
+    clients: ClientPool,
+}
+
+impl ActivityCollector {
+    pub fn new(login: Login, database: &str) -> Self {
+        ActivityCollector {
+            clients: ClientPool::new(login, database),
+        }
+    }
+
+    /// Add the backends of every YSQL server to its endpoints, and return the errors of the servers
+    /// that couldn't tell. A connection that fails is made again at the next refresh.
+    pub fn collect(&mut self, snapshot: &mut ClusterSnapshot) -> Vec<String> {
+        let mut errors = Vec::new();
+        for server in locks::ysql_servers(snapshot) {
+            match self.clients.read(&server, read_activity) {
+                Ok(activity) => {
+                    for endpoint in snapshot.endpoints.iter_mut().filter(|e| e.endpoint.hostname == server) {
+                        if matches!(endpoint.connections, Some(Connections { .. })) {
+                            endpoint.activity = activity.clone();
+                        }
+                    }
+                }
+                Err(e) => {
+                    errors.push(format!("pg_stat_activity of {}: {}", server, e));
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ysql;
+
+    #[test]
+    fn rpcz_times_and_waits() {
+        assert_eq!(micros("2022-03-27 15:55:22.587029+00"), Some(1648396522587029));
+        assert_eq!(micros("2022-03-27 17:55:22.587029+02"), Some(1648396522587029));
+        assert_eq!(micros("2022-03-27 15:55:22+00"), Some(1648396522000000));
+        assert_eq!(micros("2022-03-27 21:25:22.587029+05:30"), Some(1648396522587029));
+        assert_eq!(micros("yesterday"), None);
+        let mut activity = Activity {
+            client: String::from("10.0.0.5:50736"),
+            backend_start: 1648396522587029,
+            pid: 4242,
+            user: String::from("yugabyte"),
+            wait_event_type: String::from("Lock"),
+            wait_event: String::from("transactionid"),
+            query_id: None,
+        };
+        assert_eq!(activity.wait(), "Lock:transactionid");
+        activity.wait_event_type.clear();
+        activity.wait_event.clear();
+        assert_eq!(activity.wait(), "");
+    }
+
+    #[test]
+    #[ignore = "needs a local PostgreSQL, see ysql::test_login"]
+    fn local_activity() {
+        let login = ysql::test_login();
+        let user = login.user.clone();
+        let mut client = login.connect("127.0.0.1", "postgres").unwrap();
+        let pid: i32 = client.query_one("select pg_backend_pid()", &[]).unwrap().get(0);
+        let activity = read_activity(&mut client).unwrap();
+        let own = activity.iter().find(|a| a.pid == pid).unwrap();
+        assert_eq!(own.user, user);
+        assert!(own.client.starts_with("127.0.0.1:"));
+        // the exact microseconds, as /rpcz shows the start time.
+        let backend_start: String = client
+            .query_one("select backend_start::text from pg_stat_activity where pid = pg_backend_pid()", &[])
+            .unwrap()
+            .get(0);
+        assert_eq!(Some(own.backend_start), micros(&backend_start));
+    }
+}
@@ -0,0 +1,239 @@
+//! Offline analysis of saved /rpcz pages, such as the files in a support bundle or a recording.
+//!
+//! The files are named by host and time, with the parts separated by underscores, in any order:
+//! the time as seconds or milliseconds since the epoch, or as 20220327T155522 or 2022-03-27T15:55:22
+//! in local time, the hostname, and optionally the port after it. For example
+//! rpcz_192.168.66.80_13000_20220327T155522.json or 192.168.66.80_1648396522.json.
+//! Without a time in the name, a file in a directory named by milliseconds, as in a recording,
+//! has the time of that directory, and other files have the time they were last modified.
+//! The files with the same time make up one sample of the cluster.
+use crate::display::{self, RowFormat};
+use crate::fingerprint::fingerprint;
+use crate::snapshot::{ClusterSnapshot, Endpoint, EndpointSnapshot};
+use crate::tracking::SessionTracker;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const OTHER_PAGES: [&str; 3] = ["_api_v1_version.json", "_connections.json", "_dump-entities.json"];
+
+/// The sessions with the same fingerprint over all samples.
+#[derive(Debug)]
+pub struct FingerprintAggregate {
+    pub api: String,
+    pub fingerprint: String,
+    /// the number of times a session was seen running it.
+    pub samples: usize,
+    pub max_time_ms: u32,
+    pub total_time_ms: u64,
+}
+
+/// Read the files, and the files in the directories, and group them into a snapshot per time, sorted by time.
+pub fn read_files(paths: &[PathBuf]) -> io::Result<Vec<ClusterSnapshot>> {
+    let mut files = Vec::new();
+    for path in paths {
+        add_files(path, &mut files)?;
+    }
+    let mut samples: BTreeMap<SystemTime, Vec<EndpointSnapshot>> = BTreeMap::new();
+    for file in files {
+        let (endpoint, timestamp) = endpoint_and_time(&file)?;
+        let get_result = fs::read_to_string(&file).map_err(crate::error::Error::from);
+        samples
+            .entry(timestamp)
+            .or_default()
+            .push(EndpointSnapshot::from_rpcz(endpoint, get_result));
+    }
+    Ok(samples
+        .into_iter()
+        .map(|(timestamp, endpoints)| ClusterSnapshot {
+            timestamp,
+            endpoints,
+            entities: None,
+        })
+        .collect())
+}
+
+fn add_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path)?.map(|entry| entry.map(|e| e.path())).collect::<io::Result<Vec<PathBuf>>>()?;
+        entries.sort();
+        // a recording also holds the other pages that were read.
+        for entry in entries.into_iter().filter(|e| !OTHER_PAGES.iter().any(|page| e.to_string_lossy().ends_with(page))) {
+            add_files(&entry, files)?;
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn endpoint_and_time(file: &Path) -> io::Result<(Endpoint, SystemTime)> {
+    let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let (hostname, port, timestamp) = parse_file_name(stem);
+    let timestamp = match timestamp {
+        Some(timestamp) => timestamp,
+        None => match file
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.parse::<u64>().ok())
+        {
+            Some(millis) => UNIX_EPOCH + Duration::from_millis(millis),
+            None => fs::metadata(file)?.modified()?,
+        },
+    };
+    let hostname = hostname.unwrap_or_else(|| stem.to_string());
+    Ok((Endpoint::new(&hostname, port.as_deref().unwrap_or("-")), timestamp))
+}
+
+/// The hostname, port and time in a file name without its extension.
+fn parse_file_name(stem: &str) -> (Option<String>, Option<String>, Option<SystemTime>) {
+    let mut hostname: Option<String> = None;
+    let mut port = None;
+    let mut timestamp = None;
+    for part in stem.split('_') {
+        let digits = !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        if digits && part.len() == 10 && timestamp.is_none() {
+            timestamp = Some(UNIX_EPOCH + Duration::from_secs(part.parse().unwrap()));
+        } else if digits && part.len() == 13 && timestamp.is_none() {
+            timestamp = Some(UNIX_EPOCH + Duration::from_millis(part.parse().unwrap()));
+        } else if let Some(time) = parse_local_time(part).filter(|_| timestamp.is_none()) {
+            timestamp = Some(time);
+        } else if digits && part.len() <= 5 && hostname.is_some() && port.is_none() {
+            port = Some(part.to_string());
+        } else if !digits && part != "rpcz" && hostname.is_none() {
+            hostname = Some(part.to_string());
+        }
+    }
+    (hostname, port, timestamp)
+}
+
+fn parse_local_time(part: &str) -> Option<SystemTime> {
+    ["%Y%m%dT%H%M%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H-%M-%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(part, format).ok())
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+        .map(SystemTime::from)
+}
+
+/// Aggregate the sessions of all samples by API and fingerprint, the most seen first.
+pub fn fingerprint_aggregates(snapshots: &[ClusterSnapshot], idle: bool) -> Vec<FingerprintAggregate> {
+    let mut aggregates: HashMap<(String, String), FingerprintAggregate> = HashMap::new();
+    for snapshot in snapshots {
+        for session in snapshot.sessions(idle) {
+            let fingerprint = fingerprint(&session.query);
+            let aggregate = aggregates
+                .entry((session.api.clone(), fingerprint.clone()))
+                .or_insert_with(|| FingerprintAggregate {
+                    api: session.api.clone(),
+                    fingerprint,
+                    samples: 0,
+                    max_time_ms: 0,
+                    total_time_ms: 0,
+                });
+            aggregate.samples += 1;
+            aggregate.max_time_ms = aggregate.max_time_ms.max(session.query_time_ms);
+            aggregate.total_time_ms += session.query_time_ms as u64;
+        }
+    }
+    let mut aggregates: Vec<FingerprintAggregate> = aggregates.into_values().collect();
+    aggregates.sort_by(|a, b| {
+        b.samples
+            .cmp(&a.samples)
+            .then(b.total_time_ms.cmp(&a.total_time_ms))
+            .then(a.fingerprint.cmp(&b.fingerprint))
+    });
+    aggregates
+}
+
+/// Write the endpoints and the session table of every sample, followed by the fingerprint aggregates.
+pub fn write_analysis(out: &mut dyn Write, snapshots: &[ClusterSnapshot], idle: bool) -> io::Result<()> {
+    let mut tracker = SessionTracker::new();
+    for snapshot in snapshots {
+        writeln!(
+            out,
+            "sample {}",
+            DateTime::<Local>::from(snapshot.timestamp).format("%Y-%m-%d %H:%M:%S%.3f")
+        )?;
+        display::write_endpoints(out, &snapshot.endpoints)?;
+        writeln!(out)?;
+        display::write_sessions(out, &tracker.update(snapshot, idle), &RowFormat::default())?;
+        writeln!(out)?;
+    }
+    writeln!(out, "Fingerprints over {} samples", snapshots.len())?;
+    writeln!(out, "{:4} {:>7} {:>8} {:>8} fingerprint", "API", "samples", "max_s", "avg_s")?;
+    for aggregate in fingerprint_aggregates(snapshots, idle) {
+        writeln!(
+            out,
+            "{:4} {:7} {:8.3} {:8.3} {}",
+            aggregate.api,
+            aggregate.samples,
+            aggregate.max_time_ms as f32 / 1000.0,
+            aggregate.total_time_ms as f64 / aggregate.samples as f64 / 1000.0,
+            aggregate.fingerprint
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_file_names() {
+        let (hostname, port, timestamp) = parse_file_name("rpcz_192.168.66.80_13000_1648396522");
+        assert_eq!(hostname.as_deref(), Some("192.168.66.80"));
+        assert_eq!(port.as_deref(), Some("13000"));
+        assert_eq!(timestamp, Some(UNIX_EPOCH + Duration::from_secs(1648396522)));
+        let (hostname, port, timestamp) = parse_file_name("yb-tserver-1_20220327T155522");
+        assert_eq!(hostname.as_deref(), Some("yb-tserver-1"));
+        assert_eq!(port, None);
+        assert_eq!(timestamp, parse_local_time("2022-03-27T15:55:22"));
+        assert!(timestamp.is_some());
+        let (hostname, _, timestamp) = parse_file_name("192.168.66.81_12000_rpcz");
+        assert_eq!(hostname.as_deref(), Some("192.168.66.81"));
+        assert_eq!(timestamp, None);
+    }
+
+    #[test]
+    fn analyze_saved_files() {
+        let directory = std::env::temp_dir().join(format!("ybtop_analyze_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let sleep = |ms: u32, port: u32| {
+            format!(
+                r#"{{"connections": [{{"process_start_time": "2022-03-27 15:55:22.587029+00", "application_name": "ysqlsh", "backend_type": "client backend", "backend_status": "active", "db_name": "yugabyte", "host": "127.0.0.1", "port": "{}", "query": "select pg_sleep({});", "query_running_for_ms": {}}}]}}"#,
+                port,
+                ms / 1000,
+                ms
+            )
+        };
+        fs::write(directory.join("192.168.66.80_13000_1648396522.json"), sleep(7000, 50736)).unwrap();
+        fs::write(directory.join("192.168.66.81_13000_1648396522.json"), sleep(3000, 50737)).unwrap();
+        fs::write(directory.join("192.168.66.80_13000_1648396525.json"), sleep(10000, 50736)).unwrap();
+        fs::write(directory.join("192.168.66.81_13000_1648396525.json"), "not json").unwrap();
+        fs::write(directory.join("192.168.66.81_13000_api_v1_version.json"), "{}").unwrap();
+
+        let snapshots = read_files(std::slice::from_ref(&directory)).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].sessions(false).len(), 2);
+        assert_eq!(snapshots[1].sessions(false).len(), 1);
+        assert!(snapshots[1].endpoints[1].error.is_some());
+
+        let aggregates = fingerprint_aggregates(&snapshots, false);
+        assert_eq!(aggregates.len(), 1);
+        assert_eq!(aggregates[0].fingerprint, "select pg_sleep(?);");
+        assert_eq!(aggregates[0].samples, 3);
+        assert_eq!(aggregates[0].max_time_ms, 10000);
+
+        let mut out = Vec::new();
+        write_analysis(&mut out, &snapshots, false).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Fingerprints over 2 samples"));
+        assert!(out.ends_with("YSQL       3   10.000    6.667 select pg_sleep(?);\n"));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
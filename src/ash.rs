@@ -0,0 +1,207 @@
+//! Active session history, like Oracle's ASH: at every refresh, the active YSQL sessions of which the
+//! wait event is known are sampled, and the time since the previous refresh, at most the refresh
+//! interval, is added to the DB time of their wait class, per node and per query fingerprint. It tells whether the sessions are on CPU,
+//! wait on locks or wait on the storage RPCs.
+use crate::fingerprint;
+use crate::snapshot::{ClusterSnapshot, GeneralPresentation};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+/// The wait class of an active session that doesn't wait.
+pub const ON_CPU: &str = "CPU";
+
+/// The wait event of a row as shown in the wait column: CPU for an active session that doesn't wait.
+pub fn wait_label(row: &GeneralPresentation) -> &str {
+    match row.wait.as_deref() {
+        Some("") if !row.status.starts_with("idle") => ON_CPU,
+        Some(wait) => wait,
+        None => "",
+    }
+}
+
+/// The wait class of an active session: the type of its wait event, or CPU when it doesn't wait.
+/// Idle sessions, also idle in a transaction, and sessions of which the wait event isn't known have none.
+pub fn wait_class(row: &GeneralPresentation) -> Option<&str> {
+    if row.status.is_empty() || row.status.starts_with("idle") {
+        return None;
+    }
+    let wait = wait_label(row);
+    match wait.split_once(':') {
+        Some((class, _)) => Some(class),
+        None if wait.is_empty() => None,
+        None => Some(wait),
+    }
+}
+
+/// The DB time in seconds per wait class.
+pub type DbTime = BTreeMap<String, f64>;
+
+pub struct AshTracker {
+    previous: Option<SystemTime>,
+    /// the most time a sample counts for.
+    refresh: Duration,
+    /// the refreshes with sessions of which the wait event is known.
+    pub samples: u32,
+    pub by_node: BTreeMap<String, DbTime>,
+    pub by_fingerprint: BTreeMap<String, DbTime>,
+}
+
+impl Default for AshTracker {
+    fn default() -> Self {
+        AshTracker {
+            previous: None,
+            refresh: Duration::MAX,
+            samples: 0,
+            by_node: BTreeMap::new(),
+            by_fingerprint: BTreeMap::new(),
+        }
+    }
+}
+
+impl AshTracker {
+    pub fn new() -> Self {
+        AshTracker::default()
+    }
+
+    /// Count a sample for at most the refresh interval, so a pause or a stalled refresh isn't taken as
+    /// DB time of the sessions of the next refresh.
+    pub fn with_refresh_interval(mut self, refresh: Duration) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Sample the active sessions of a snapshot. The first snapshot only starts the time.
+    pub fn update(&mut self, snapshot: &ClusterSnapshot) {
+        let seconds = self
+            .previous
+            .and_then(|previous| snapshot.sampled_since(previous, self.refresh))
+            .map(|sampled| sampled.as_secs_f64());
+        self.previous = Some(snapshot.timestamp);
+        let seconds = match seconds {
+            Some(seconds) => seconds,
+            None => return,
+        };
+        let mut sampled = false;
+        for row in snapshot.sessions(false) {
+            if let Some(class) = wait_class(&row) {
+                *self.by_node.entry(row.server.clone()).or_default().entry(class.to_string()).or_default() += seconds;
+                *self
+                    .by_fingerprint
+                    .entry(fingerprint::fingerprint(&row.query))
+                    .or_default()
+                    .entry(class.to_string())
+                    .or_default() += seconds;
+                sampled = true;
+            }
+        }
+        if sampled {
+            self.samples += 1;
+        }
+    }
+}
+
+/// The wait classes of the DB times, CPU first.
+fn classes<'a>(db_times: impl Iterator<Item = &'a DbTime>) -> Vec<&'a str> {
+    let classes: BTreeSet<&str> = db_times.flat_map(|db_time| db_time.keys().map(String::as_str)).collect();
+    let mut classes: Vec<&str> = classes.into_iter().collect();
+    classes.sort_by_key(|class| (*class != ON_CPU, *class));
+    classes
+}
+
+fn total(db_time: &DbTime) -> f64 {
+    db_time.values().sum()
+}
+
+/// Write the DB time by wait class per node, and of the fingerprints with the most DB time, at most limit.
+pub fn write_ash(out: &mut dyn Write, tracker: &AshTracker, limit: usize) -> io::Result<()> {
+    if tracker.samples == 0 {
+        return writeln!(
+            out,
+            "No wait events were sampled yet, they are read with --pg-stat-activity or from the /rpcz of versions that have them"
+        );
+    }
+    let classes = classes(tracker.by_node.values());
+    let columns = |db_time: &DbTime| {
+        classes
+            .iter()
+            .map(|class| format!("{:>10.1}", db_time.get(*class).copied().unwrap_or_default()))
+            .collect::<Vec<String>>()
+            .join(" ")
+    };
+    let header: Vec<String> = classes.iter().map(|class| format!("{:>10}", class)).collect();
+    writeln!(out, "DB time in seconds by wait class, from {} samples", tracker.samples)?;
+    writeln!(out, "{:20} {} {:>10}", "node", header.join(" "), "total")?;
+    for (node, db_time) in &tracker.by_node {
+        writeln!(out, "{:20} {} {:>10.1}", node, columns(db_time), total(db_time))?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{} {:>10} fingerprint", header.join(" "), "total")?;
+    let mut fingerprints: Vec<(&String, &DbTime)> = tracker.by_fingerprint.iter().collect();
+    fingerprints.sort_by(|a, b| total(b.1).total_cmp(&total(a.1)).then(a.0.cmp(b.0)));
+    for (fingerprint, db_time) in fingerprints.into_iter().take(limit) {
+        writeln!(out, "{} {:>10.1} {}", columns(db_time), total(db_time), fingerprint)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::fixtures;
+
+    fn snapshot(seconds: u64, waits: &[(&str, &str, &str)]) -> ClusterSnapshot {
+        let connections: Vec<String> = waits
+            .iter()
+            .enumerate()
+            .map(|(i, (status, wait_event_type, query))| {
+                format!(
+                    r#"{{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_status": "{}", "host": "10.0.0.5", "port": "{}", "query": "{}", "wait_event_type": "{}", "wait_event": "{}"}}"#,
+                    status, 50000 + i, query, wait_event_type, if wait_event_type.is_empty() { "" } else { "event" }
+                )
+            })
+            .collect();
+        fixtures::snapshot(seconds, &[("192.168.66.80:13000", &fixtures::ysql(&connections))])
+    }
+
+    #[test]
+    fn db_time_by_wait_class() {
+        let waits = [
+            ("active", "", "select * from t where id = 1"),
+            ("active", "Lock", "update t set a = 1 where id = 2"),
+            ("active", "Lock", "update t set a = 2 where id = 3"),
+            ("idle in transaction", "Client", "update t set a = 3 where id = 4"),
+        ];
+        let mut tracker = AshTracker::new();
+        tracker.update(&snapshot(100, &waits));
+        assert_eq!(tracker.samples, 0);
+        tracker.update(&snapshot(103, &waits));
+        tracker.update(&snapshot(105, &waits[..1]));
+        assert_eq!(tracker.samples, 2);
+        let node = &tracker.by_node["192.168.66.80"];
+        assert_eq!(node.get(ON_CPU), Some(&5.0));
+        assert_eq!(node.get("Lock"), Some(&6.0));
+        assert_eq!(node.get("Client"), None);
+        assert_eq!(tracker.by_fingerprint["update t set a = ? where id = ?"].get("Lock"), Some(&6.0));
+
+        let mut out = Vec::new();
+        write_ash(&mut out, &tracker, 10).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[1], "node                        CPU       Lock      total");
+        assert_eq!(lines[2], "192.168.66.80               5.0        6.0       11.0");
+        assert_eq!(lines[5], "       0.0        6.0        6.0 update t set a = ? where id = ?");
+    }
+
+    #[test]
+    fn pause_is_not_db_time() {
+        let waits = [("active", "Lock", "update t set a = 1 where id = 2")];
+        let mut tracker = AshTracker::new().with_refresh_interval(Duration::from_secs(3));
+        tracker.update(&snapshot(100, &waits));
+        tracker.update(&snapshot(103, &waits));
+        // paused for a minute, and the sessions of the next refresh are counted for a refresh interval.
+        tracker.update(&snapshot(163, &waits));
+        assert_eq!(tracker.samples, 2);
+        assert_eq!(tracker.by_node["192.168.66.80"].get("Lock"), Some(&6.0));
+    }
+}
@@ -0,0 +1,178 @@
+//! The commands that can be typed while the display runs, each followed by enter.
+use crate::ysql::Signal;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// What the display shows under the endpoints.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum View {
+    Sessions,
+    /// the YCQL clients ranked by calls per second.
+    Throughput,
+    /// the connections counted by server, client, application, db or keyspace and state.
+    Inventory,
+    /// everything about the selected session.
+    Detail,
+    /// the plan of the statement of the selected session.
+    Plan,
+    /// the YSQL sessions that wait on a lock, under the sessions they wait on.
+    Locks,
+    /// the DB time by wait class per node and per query fingerprint.
+    Ash,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// the wait for the next refresh is over.
+    Refresh,
+    Quit,
+    Pause,
+    Step,
+    Faster,
+    Slower,
+    /// show the view, or when it is shown, the sessions.
+    ToggleView(View),
+    /// add filters, or without them, prompt for them.
+    Filter(Option<String>),
+    ClearFilters,
+    /// search the queries for a regex, or without it, prompt for it.
+    Search(Option<String>),
+    /// select the next row that matches the search, or going backward, the previous one.
+    NextMatch(bool),
+    /// hide the rows that don't match the search, or show them again.
+    HideNonMatching,
+    /// select the next row, or going backward, the previous one.
+    Select(bool),
+    /// cancel the query of the selected YSQL session, or terminate the session.
+    SignalBackend(Signal),
+    /// explain the statement of the selected YSQL session, or with true, explain analyze it.
+    Explain(bool),
+    /// show the next page of the plan, or going backward, the previous one.
+    Scroll(bool),
+    Unknown(String),
+}
+
+pub const HELP: &str = "p:pause s:step +/-:speed t:throughput i:inventory l:locks a:ash o:filter =:unfilter /:search n/N:next/previous m:only matches j/k:select d:detail c:cancel K:terminate e:explain f/b:scroll q:quit";
+
+pub fn parse_command(line: &str) -> Command {
+    match line.trim() {
+        "q" => Command::Quit,
+        "p" => Command::Pause,
+        "s" => Command::Step,
+        "+" => Command::Faster,
+        "-" => Command::Slower,
+        "t" => Command::ToggleView(View::Throughput),
+        "i" => Command::ToggleView(View::Inventory),
+        "l" => Command::ToggleView(View::Locks),
+        "a" => Command::ToggleView(View::Ash),
+        "o" => Command::Filter(None),
+        "=" => Command::ClearFilters,
+        "/" => Command::Search(None),
+        "n" => Command::NextMatch(true),
+        "N" => Command::NextMatch(false),
+        "m" => Command::HideNonMatching,
+        "j" => Command::Select(true),
+        "k" => Command::Select(false),
+        "d" => Command::ToggleView(View::Detail),
+        "c" => Command::SignalBackend(Signal::Cancel),
+        "K" => Command::SignalBackend(Signal::Terminate),
+        "e" => Command::Explain(false),
+        "E" => Command::Explain(true),
+        "f" => Command::Scroll(true),
+        "b" => Command::Scroll(false),
+        other => {
+            if let Some(filters) = other.strip_prefix("o ") {
+                Command::Filter(Some(filters.to_string()))
+            } else if let Some(pattern) = other.strip_prefix('/') {
+                Command::Search(Some(pattern.to_string()))
+            } else {
+                Command::Unknown(other.to_string())
+            }
+        }
+    }
+}
+
+/// Read the commands from stdin in a thread, so the display can wait for a command and the refresh at the same time.
+pub fn read_commands() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) => {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    });
+    receiver
+}
+
+/// Wait for a command until the wait is over, or without a wait, until there is a command.
+pub fn next_command(commands: &Receiver<String>, wait: Option<Duration>) -> Command {
+    match wait {
+        Some(wait) => match commands.recv_timeout(wait) {
+            Ok(line) => parse_command(&line),
+            Err(RecvTimeoutError::Timeout) => Command::Refresh,
+            Err(RecvTimeoutError::Disconnected) => {
+                // stdin is closed, so there are no commands to wait for.
+                thread::sleep(wait);
+                Command::Refresh
+            }
+        },
+        None => match commands.recv() {
+            Ok(line) => parse_command(&line),
+            Err(_) => Command::Quit,
+        },
+    }
+}
+
+/// Ask a question, and wait for the answer.
+pub fn prompt(commands: &Receiver<String>, question: &str) -> Option<String> {
+    print!("{}", question);
+    io::stdout().flush().ok()?;
+    commands.recv().ok()
+}
+
+/// Ask a yes or no question, and whether the answer is y.
+pub fn confirm(commands: &Receiver<String>, question: &str) -> bool {
+    prompt(commands, &format!("{} (y/n)? ", question)).is_some_and(|answer| answer.trim() == "y")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse_command("q"), Command::Quit);
+        assert_eq!(parse_command(" p "), Command::Pause);
+        assert_eq!(parse_command("+"), Command::Faster);
+        assert_eq!(parse_command("t"), Command::ToggleView(View::Throughput));
+        assert_eq!(parse_command("o"), Command::Filter(None));
+        assert_eq!(parse_command("o db=orders"), Command::Filter(Some(String::from("db=orders"))));
+        assert_eq!(parse_command("/"), Command::Search(None));
+        assert_eq!(parse_command("/pg_sleep|insert"), Command::Search(Some(String::from("pg_sleep|insert"))));
+        assert_eq!(parse_command("N"), Command::NextMatch(false));
+        assert_eq!(parse_command("d"), Command::ToggleView(View::Detail));
+        assert_eq!(parse_command("k"), Command::Select(false));
+        assert_eq!(parse_command("K"), Command::SignalBackend(Signal::Terminate));
+        assert_eq!(parse_command("E"), Command::Explain(true));
+        assert_eq!(parse_command("b"), Command::Scroll(false));
+        assert_eq!(parse_command("x"), Command::Unknown(String::from("x")));
+    }
+
+    #[test]
+    fn next_command_waits_for_refresh() {
+        let (sender, receiver) = mpsc::channel();
+        assert_eq!(next_command(&receiver, Some(Duration::from_millis(1))), Command::Refresh);
+        sender.send(String::from("s")).unwrap();
+        assert_eq!(next_command(&receiver, None), Command::Step);
+        drop(sender);
+        assert_eq!(next_command(&receiver, None), Command::Quit);
+    }
+}
@@ -0,0 +1,111 @@
+use serde_derive::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+/// The YSQL connection manager's /connections output, served on the YSQL web port.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConnectionManagerStats {
+    #[serde(alias = "pool")]
+    pub pools: Vec<Pool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Pool {
+    pub database_name: String,
+    pub user_name: String,
+    pub active_logical_connections: u32,
+    pub queued_logical_connections: u32,
+    pub idle_or_pending_logical_connections: u32,
+    pub active_physical_connections: u32,
+    pub idle_physical_connections: u32,
+    pub avg_wait_time_ns: Option<u64>,
+    pub qps: Option<u64>,
+    pub tps: Option<u64>,
+}
+
+impl Pool {
+    /// A pool is saturated when clients are waiting and there is no idle backend to give them.
+    pub fn saturated(&self) -> bool {
+        self.queued_logical_connections > 0 && self.idle_physical_connections == 0
+    }
+}
+
+pub fn parse_connection_manager(http_data: String) -> Option<ConnectionManagerStats> {
+    serde_json::from_str(&http_data).ok()
+}
+
+pub fn write_pools(out: &mut dyn Write, node_stats: &[(&str, &ConnectionManagerStats)]) -> io::Result<()> {
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{:20} {:10} {:10} {:>7} {:>7} {:>7} {:>8} {:>8} {:>9}",
+        "conn mgr server", "db", "user", "cl_act", "cl_wait", "cl_idle", "be_act", "be_idle", "avg_wait_ms"
+    )?;
+    for (server, stats) in node_stats {
+        // the control connection is the connection manager's own, not a client pool
+        for pool in stats.pools.iter().filter(|p| p.database_name != "control_connection") {
+            writeln!(
+                out,
+                "{:20} {:10} {:10} {:7} {:7} {:7} {:8} {:8} {:9.3}{}",
+                server,
+                pool.database_name,
+                pool.user_name,
+                pool.active_logical_connections,
+                pool.queued_logical_connections,
+                pool.idle_or_pending_logical_connections,
+                pool.active_physical_connections,
+                pool.idle_physical_connections,
+                pool.avg_wait_time_ns.unwrap_or_default() as f64 / 1_000_000.0,
+                if pool.saturated() { " SATURATED" } else { "" }
+            )?;
+        }
+    }
+    writeln!(
+        out,
+        "YSQL clients on {} are connection manager backends, not the application's address.",
+        node_stats.iter().map(|(server, _)| *server).collect::<Vec<&str>>().join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_connection_manager_pools() {
+        let http_result = r#"
+{
+    "pools": [
+        {
+            "database_name": "control_connection",
+            "user_name": "control_connection",
+            "active_logical_connections": 0,
+            "queued_logical_connections": 0,
+            "idle_or_pending_logical_connections": 0,
+            "active_physical_connections": 0,
+            "idle_physical_connections": 1,
+            "avg_wait_time_ns": 0,
+            "qps": 0,
+            "tps": 0
+        },
+        {
+            "database_name": "yugabyte",
+            "user_name": "yugabyte",
+            "active_logical_connections": 10,
+            "queued_logical_connections": 4,
+            "idle_or_pending_logical_connections": 30,
+            "active_physical_connections": 10,
+            "idle_physical_connections": 0,
+            "avg_wait_time_ns": 2500000,
+            "qps": 812,
+            "tps": 790
+        }
+    ]
+}
+        "#;
+        let stats = parse_connection_manager(http_result.to_string()).unwrap();
+        assert_eq!(stats.pools.len(), 2);
+        assert!(!stats.pools[0].saturated());
+        assert!(stats.pools[1].saturated());
+        assert_eq!(stats.pools[1].queued_logical_connections, 4);
+    }
+}
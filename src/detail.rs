@@ -0,0 +1,255 @@
+//! The detail pane of a selected session: its full query pretty printed, the statements of a batch
+//! with their decoded params, the hot tables it reads or writes, and every field the /rpcz has of it,
+//! with the fields ybtop doesn't know apart.
+use crate::syntax;
+use crate::tablets::{self, Hotspot};
+use crate::tracking::TrackedRow;
+use serde_json::Value;
+use std::io::{self, Write};
+
+/// The keywords that start a line of a pretty printed query.
+const CLAUSES: [&str; 22] = [
+    "select", "from", "where", "group", "order", "having", "limit", "offset", "values", "set", "returning", "union",
+    "intersect", "except", "join", "left", "right", "inner", "full", "cross", "and", "or",
+];
+
+/// The words before join that are on its line.
+const JOIN_KINDS: [&str; 7] = ["left", "right", "inner", "full", "cross", "outer", "natural"];
+
+/// The words of a query, keeping quoted text together, with how much each one opens parentheses.
+fn words(query: &str) -> Vec<(String, isize)> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+    for c in query.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == '(' => depth += 1,
+            None if c == ')' => depth -= 1,
+            None if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push((std::mem::take(&mut word), depth));
+                    depth = 0;
+                }
+                continue;
+            }
+            None => {}
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push((word, depth));
+    }
+    words
+}
+
+/// Put the clauses of a SQL or CQL query on lines of their own, and and and or indented,
+/// indented further in parentheses. The and of between stays on the line.
+pub fn pretty_print(query: &str) -> String {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut depth: isize = 0;
+    let mut previous = String::new();
+    let mut between = false;
+    for (word, opens) in words(query) {
+        let keyword = word.trim_start_matches('(').to_lowercase();
+        let clause = CLAUSES.contains(&keyword.as_str())
+            && !(keyword == "join" && JOIN_KINDS.contains(&previous.as_str()))
+            && !(keyword == "and" && between);
+        match keyword.as_str() {
+            "between" => between = true,
+            "and" => between = false,
+            _ => {}
+        }
+        if clause && !line.is_empty() {
+            lines.push(line);
+            let leading = (word.len() - word.trim_start_matches('(').len()) as isize;
+            let indent = depth + leading + isize::from(keyword == "and" || keyword == "or");
+            line = "  ".repeat(indent.max(0) as usize);
+        } else if !line.trim_start().is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word);
+        depth += opens;
+        previous = keyword;
+    }
+    lines.push(line);
+    lines.join("\n")
+}
+
+/// Decode the params of a YCQL statement as /rpcz shows them: a list of which the text values are
+/// readable, and the numbers are their big endian bytes. Those are shown as numbers, other unreadable
+/// values are escaped.
+pub fn decode_params(params: &str) -> Vec<String> {
+    let params = params.trim();
+    let params = params.strip_prefix('[').unwrap_or(params);
+    let params = params.strip_suffix(']').unwrap_or(params);
+    params
+        .split(", ")
+        .map(|value| {
+            if !value.chars().any(char::is_control) {
+                return value.to_string();
+            }
+            let bytes: Option<Vec<u8>> = value.chars().map(|c| u8::try_from(c).ok()).collect();
+            match bytes {
+                Some(bytes) if bytes.len() == 4 => i32::from_be_bytes(bytes.try_into().unwrap()).to_string(),
+                Some(bytes) if bytes.len() == 8 => i64::from_be_bytes(bytes.try_into().unwrap()).to_string(),
+                Some(bytes) if bytes.len() < 8 => bytes.iter().fold(0u64, |n, b| n << 8 | *b as u64).to_string(),
+                _ => syntax::printable(value),
+            }
+        })
+        .collect()
+}
+
+/// The fields of a json value as name and value, with the names of nested fields joined by dots.
+fn flatten(name: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => {}
+        Value::Object(object) => {
+            for (key, value) in object {
+                let key = syntax::printable(key);
+                let name = if name.is_empty() { key } else { format!("{}.{}", name, key) };
+                flatten(&name, value, fields);
+            }
+        }
+        Value::Array(array) => {
+            for (index, value) in array.iter().enumerate() {
+                flatten(&format!("{}[{}]", name, index), value, fields);
+            }
+        }
+        Value::String(text) => fields.push((name.to_string(), syntax::printable(text))),
+        other => fields.push((name.to_string(), other.to_string())),
+    }
+}
+
+/// Write the detail pane of a row, with the fields of its session when it is still in the /rpcz and
+/// those of them ybtop doesn't know, the hot tables, when the masters are read, that its query names, and with color, the query and
+/// statements colored. The text from the cluster is shown with its control characters escaped.
+pub fn write_detail(
+    out: &mut dyn Write,
+    tracked: &TrackedRow,
+    raw: Option<&Value>,
+    extra: Option<&Value>,
+    hot_tables: &[Hotspot],
+    color: bool,
+) -> io::Result<()> {
+    let row = &tracked.row;
+    let paint = |text: &str| {
+        let text = syntax::printable(text);
+        if color { syntax::colorize(&text) } else { text }
+    };
+    writeln!(out, "{} session on {} from {}", row.api, row.server, row.client)?;
+    writeln!(
+        out,
+        "key/db {}  application {}  status {}  time_s {:.3}  state {}",
+        syntax::printable(&row.keyspace_db_name),
+        syntax::printable(&row.application),
+        row.status,
+        row.query_time_ms as f32 / 1000.0,
+        tracked.state.label()
+    )?;
+    if let Some(activity) = &row.activity {
+        writeln!(
+            out,
+            "pid {}  user {}  wait {}  query_id {}",
+            activity.pid,
+            activity.user,
+            activity.wait(),
+            activity.query_id.map(|id| id.to_string()).unwrap_or_default()
+        )?;
+    }
+    // the params of the statements of a YCQL call.
+    let params: Vec<Option<&str>> = raw
+        .and_then(|raw| raw.pointer("/call_in_flight/cql_details/call_details"))
+        .and_then(Value::as_array)
+        .map(|details| details.iter().map(|d| d.get("params").and_then(Value::as_str)).collect())
+        .unwrap_or_default();
+    writeln!(out)?;
+    writeln!(out, "query:")?;
+    for line in pretty_print(&row.query).lines() {
+        writeln!(out, "  {}", paint(line))?;
+    }
+    if row.statements.is_empty() {
+        if let Some(Some(params)) = params.first() {
+            writeln!(out, "params: [{}]", decode_params(params).join(", "))?;
+        }
+    } else {
+        writeln!(out)?;
+        writeln!(out, "statements:")?;
+        for (n, statement) in row.statements.iter().enumerate() {
+            writeln!(out, "{:4}: {}", n + 1, paint(statement))?;
+            if let Some(Some(params)) = params.get(n) {
+                writeln!(out, "      params: [{}]", decode_params(params).join(", "))?;
+            }
+        }
+    }
+    let hot_tables = tablets::hot_tables_of(row, hot_tables);
+    if !hot_tables.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{:40} {:>6} {:>10} {:>8}", "hot tables of the query", "calls", "elapsed_s", "max_s")?;
+        for hotspot in hot_tables {
+            writeln!(
+                out,
+                "{:40} {:6} {:10.3} {:8.3}",
+                hotspot.name,
+                hotspot.calls,
+                hotspot.elapsed_ms as f32 / 1000.0,
+                hotspot.max_elapsed_ms as f32 / 1000.0
+            )?;
+        }
+    }
+    writeln!(out)?;
+    writeln!(out, "fields:")?;
+    match raw {
+        Some(raw) => {
+            let mut fields = Vec::new();
+            flatten("", raw, &mut fields);
+            for (name, value) in fields {
+                writeln!(out, "  {}: {}", name, value)?;
+            }
+        }
+        None => writeln!(out, "  the session is no longer in /rpcz")?,
+    }
+    let mut unknown = Vec::new();
+    if let Some(extra) = extra {
+        flatten("", extra, &mut unknown);
+    }
+    if !unknown.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "fields ybtop doesn't know:")?;
+        for (name, value) in unknown {
+            writeln!(out, "  {}: {}", name, value)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty_print_query() {
+        assert_eq!(
+            pretty_print("SELECT a, count(*) FROM t LEFT JOIN u ON t.id = u.id WHERE a = 'x and y' AND b IN (SELECT b FROM v) GROUP BY a"),
+            "SELECT a, count(*)\nFROM t\nLEFT JOIN u ON t.id = u.id\nWHERE a = 'x and y'\n  AND b IN\n  (SELECT b\n  FROM v)\nGROUP BY a"
+        );
+        assert_eq!(pretty_print("select pg_sleep(120);"), "select pg_sleep(120);");
+        assert_eq!(
+            pretty_print("select * from t where a between 1 and 10 and b not between 'x' and 'y' or c = 2"),
+            "select *\nfrom t\nwhere a between 1 and 10\n  and b not between 'x' and 'y'\n  or c = 2"
+        );
+    }
+
+    #[test]
+    fn decode_binary_params() {
+        assert_eq!(
+            decode_params("[2008-06, Alabama, \u{0}\u{0}\u{0}\u{0}, n/a, \u{0}\u{0}\u{1C},, \u{0}\u{0}\u{0}\t, \u{0}\u{0}D.]"),
+            vec!["2008-06", "Alabama", "0", "n/a", "7212", "9", "17454"]
+        );
+        assert_eq!(decode_params("[\u{1}\u{3b1}]"), vec!["\\u{1}\u{3b1}"]);
+    }
+}
@@ -0,0 +1,846 @@
+//! The terminal display: a top-like session list that is refreshed every interval.
+use crate::activity::ActivityCollector;
+use crate::ash::{self, AshTracker};
+use crate::commands::{self, Command, View};
+use crate::connmgr;
+use crate::detail;
+use crate::filter::{self, Filter};
+use crate::history::History;
+use crate::inventory;
+use crate::load::{self, LoadTracker};
+use crate::locks::{self, BlockedBackend, LockCollector};
+use crate::recording::{RecordingSource, ReplaySource};
+use crate::search::{self, Search};
+use crate::severity::{self, Thresholds};
+use crate::skew::{self, ApiSkew, SkewTracker};
+use crate::slowlog::{self, SlowQueryLog};
+use crate::snapshot::{ClusterSnapshot, Collector, Endpoint, EndpointSnapshot};
+use crate::source::{HttpSource, Schedule, Source};
+use crate::syntax;
+use crate::tablets;
+use crate::throughput::{self, ConnectionThroughput, ThroughputTracker};
+use crate::tracking::{self, RowKey, SessionTracker, TrackedRow};
+use crate::ysql::{self, Backend, Login};
+use chrono::{DateTime, Local};
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::process;
+use std::time::Duration;
+
+/// The number of recently finished long queries that are shown.
+const SLOW_QUERIES: usize = 10;
+
+/// The number of query fingerprints in the DB time by wait class.
+const ASH_FINGERPRINTS: usize = 20;
+
+/// The number of refreshes in the sparkline of the active sessions.
+const SPARKLINE_REFRESHES: usize = 30;
+
+/// How the display collects and shows the sessions.
+pub struct Settings {
+    /// seconds between refreshes.
+    pub refresh_interval: u64,
+    pub idle: bool,
+    pub masters: Vec<String>,
+    pub connection_manager: bool,
+    /// write every page that is read to this directory.
+    pub record: Option<PathBuf>,
+    /// replay a recording instead of reading the endpoints.
+    pub replay: Option<PathBuf>,
+    /// add the sessions of every refresh to this SQLite database.
+    pub history: Option<PathBuf>,
+    /// seconds a query must have been seen running to be caught when it finishes.
+    pub slow_threshold: u64,
+    /// append the caught queries to this file.
+    pub slow_log: Option<PathBuf>,
+    /// the max/mean of the active sessions per server from which an API is skewed.
+    pub skew_threshold: f64,
+    /// warn when the same server is skewed for more than this many refreshes.
+    pub skew_refreshes: usize,
+    /// show only the sessions that match all filters.
+    pub filters: Vec<Filter>,
+    /// color the queries, the statuses and the times.
+    pub color: bool,
+    /// the times from which a query is shown yellow, red and blinking.
+    pub time_colors: Thresholds,
+    /// how to log in to YSQL to cancel or terminate a session, explain a statement or read the locks.
+    pub login: Login,
+    /// the database to connect to, to read the locks and pg_stat_activity.
+    pub ysql_database: String,
+    /// read the blocking chains of every YSQL server at every refresh.
+    pub locks: bool,
+    /// read pg_stat_activity of every YSQL server at every refresh, and add it to the YSQL sessions.
+    pub pg_stat_activity: bool,
+}
+
+pub fn display_clients(hostname_vec: Vec<&str>, port_vec: Vec<&str>, settings: Settings) {
+    let mut endpoints = Endpoint::from_hosts_and_ports(&hostname_vec, &port_vec);
+    let mut masters = settings.masters.clone();
+    let source: Box<dyn Source> = match &settings.replay {
+        // a replay reads the endpoints and masters that are in the recording.
+        Some(directory) => Box::new(
+            ReplaySource::new(directory)
+                .and_then(|replay| {
+                    endpoints = replay.endpoints("/rpcz")?;
+                    masters = replay.endpoints("/dump-entities")?.iter().map(Endpoint::to_string).collect();
+                    Ok(replay)
+                })
+                .unwrap_or_else(|e| {
+                    eprintln!("Error reading recording: {}", e);
+                    process::exit(1);
+                }),
+        ),
+        None => Box::new(HttpSource::default()),
+    };
+    let source: Box<dyn Source> = match &settings.record {
+        Some(directory) => Box::new(RecordingSource::new(source, directory).unwrap_or_else(|e| {
+            eprintln!("Error creating recording: {}", e);
+            process::exit(1);
+        })),
+        None => source,
+    };
+    let mut collector = Collector::with_source(endpoints, source);
+    collector.masters = masters.clone();
+    collector.connection_manager = settings.connection_manager;
+    let mut history = settings.history.as_ref().map(|path| {
+        History::open(path)
+            .map(|history| history.with_refresh_interval(Duration::from_secs(settings.refresh_interval)))
+            .unwrap_or_else(|e| {
+                eprintln!("Error opening history: {}", e);
+                process::exit(1);
+            })
+    });
+    let slow_queries = SlowQueryLog::new(Duration::from_secs(settings.slow_threshold), SLOW_QUERIES);
+    let slow_queries = match &settings.slow_log {
+        Some(path) => slow_queries.with_file(path).unwrap_or_else(|e| {
+            eprintln!("Error opening slow query log: {}", e);
+            process::exit(1);
+        }),
+        None => slow_queries,
+    };
+    let commands = commands::read_commands();
+    let mut paused = false;
+    let mut speed: f64 = 1.0;
+    let mut screen = Screen::new(settings.idle, slow_queries, SkewTracker::new(settings.skew_threshold));
+    screen.skew_refreshes = settings.skew_refreshes;
+    screen.filters = settings.filters.clone();
+    screen.color = settings.color;
+    screen.time_colors = settings.time_colors;
+    screen.ash = AshTracker::new().with_refresh_interval(Duration::from_secs(settings.refresh_interval));
+    let mut lock_collector = settings.locks.then(|| LockCollector::new(settings.login.clone(), &settings.ysql_database));
+    // a replay has no pg_stat_activity of its time.
+    let mut activity_collector =
+        (settings.pg_stat_activity && settings.replay.is_none()).then(|| ActivityCollector::new(settings.login.clone(), &settings.ysql_database));
+    let mut snapshot = collect(&mut collector, &mut activity_collector, &mut screen, &mut history);
+    update(&mut screen, &snapshot, &mut lock_collector);
+    loop {
+        std::process::Command::new("clear").status().unwrap();
+        screen.width = terminal_size("COLUMNS", "cols");
+        screen.height = terminal_size("LINES", "lines");
+        let mode = match (collector.position(), paused) {
+            (Some((refresh, refreshes)), true) => format!("replay at {}x, refresh {}/{}, paused", speed, refresh, refreshes),
+            (Some((refresh, refreshes)), false) => format!("replay at {}x, refresh {}/{}", speed, refresh, refreshes),
+            (None, true) => String::from("paused"),
+            (None, false) => format!("every {}s", settings.refresh_interval),
+        };
+        println!(
+            "ybtop {} {}  {}",
+            DateTime::<Local>::from(snapshot.timestamp).format("%Y-%m-%d %H:%M:%S"),
+            mode,
+            commands::HELP
+        );
+        render(&mut io::stdout().lock(), &snapshot, &screen, &masters).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        // a replay waits as long as there was between the recorded refreshes, at its speed.
+        let wait = match collector.schedule() {
+            Schedule::Live => Some(Duration::from_secs(settings.refresh_interval)),
+            Schedule::Next(next) => Some(next.duration_since(snapshot.timestamp).unwrap_or_default().div_f64(speed)),
+            Schedule::Finished => None,
+        };
+        let command = commands::next_command(&commands, if paused { None } else { wait });
+        screen.message = None;
+        let refresh = match command {
+            Command::Refresh => !paused,
+            Command::Step => !matches!(collector.schedule(), Schedule::Finished),
+            Command::Quit => process::exit(0),
+            Command::Pause => {
+                paused = !paused;
+                false
+            }
+            Command::Faster => {
+                speed *= 2.0;
+                false
+            }
+            Command::Slower => {
+                speed /= 2.0;
+                false
+            }
+            Command::ToggleView(view) => {
+                screen.view = if screen.view == view { View::Sessions } else { view };
+                if screen.view == View::Detail && screen.selected.is_none() {
+                    screen.select_row(true);
+                }
+                false
+            }
+            Command::Filter(filters) => {
+                let filters = filters.or_else(|| commands::prompt(&commands, "filter (field=value,...): "));
+                match filter::parse_filters(filters.as_deref().unwrap_or_default()) {
+                    Ok(filters) => screen.filters.extend(filters),
+                    Err(e) => screen.message = Some(e),
+                }
+                false
+            }
+            Command::ClearFilters => {
+                screen.filters.clear();
+                false
+            }
+            Command::Search(pattern) => {
+                let pattern = pattern.or_else(|| commands::prompt(&commands, "search (regex, empty to stop): "));
+                match pattern.as_deref().map(str::trim).unwrap_or_default() {
+                    "" => {
+                        screen.search = None;
+                        screen.selected = None;
+                    }
+                    pattern => match Search::new(pattern) {
+                        Ok(search) => {
+                            screen.search = Some(search);
+                            screen.select_match(true);
+                        }
+                        Err(e) => screen.message = Some(e.to_string()),
+                    },
+                }
+                false
+            }
+            Command::NextMatch(forward) => {
+                screen.select_match(forward);
+                false
+            }
+            Command::HideNonMatching => {
+                if let Some(search) = &mut screen.search {
+                    search.hide = !search.hide;
+                }
+                false
+            }
+            Command::Select(forward) => {
+                screen.select_row(forward);
+                false
+            }
+            Command::SignalBackend(signal) => {
+                let backend = match screen.selected_row() {
+                    Some(tracked) => Backend::from_row(&tracked.row, snapshot.raw_fields(&tracked.row).as_ref()),
+                    None => Err(String::from("select a YSQL session with j and k first")),
+                };
+                screen.message = Some(match backend {
+                    Ok(backend) => {
+                        let question = format!(
+                            "{} the query of {}:{} on {}",
+                            signal.verb(),
+                            backend.client_addr,
+                            backend.client_port,
+                            backend.server
+                        );
+                        if commands::confirm(&commands, &question) {
+                            ysql::signal(&settings.login, &backend, signal).unwrap_or_else(|e| e.to_string())
+                        } else {
+                            format!("did not {}", signal.verb())
+                        }
+                    }
+                    Err(e) => e,
+                });
+                false
+            }
+            Command::Explain(analyze) => {
+                if screen.view == View::Plan {
+                    screen.view = View::Detail;
+                } else {
+                    explain(&mut screen, &settings.login, &commands, analyze);
+                }
+                false
+            }
+            Command::Scroll(forward) => {
+                screen.scroll(forward);
+                false
+            }
+            Command::Unknown(_) => false,
+        };
+        if refresh {
+            snapshot = collect(&mut collector, &mut activity_collector, &mut screen, &mut history);
+            update(&mut screen, &snapshot, &mut lock_collector);
+        }
+    }
+}
+
+/// Collect a snapshot, with pg_stat_activity when it is read, and add it to the history.
+fn collect(
+    collector: &mut Collector,
+    activity_collector: &mut Option<ActivityCollector>,
+    screen: &mut Screen,
+    history: &mut Option<History>,
+) -> ClusterSnapshot {
+    let mut snapshot = collector.collect().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    if let Some(activity_collector) = activity_collector {
+        screen.activity_errors = activity_collector.collect(&mut snapshot);
+    }
+    if let Some(history) = history {
+        history.write(&snapshot).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+    }
+    snapshot
+}
+
+/// A size of the terminal, when the output is one: from an environment variable, such as COLUMNS,
+/// or else from tput, such as tput cols.
+fn terminal_size(variable: &str, capability: &str) -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    if let Some(size) = env::var(variable).ok().and_then(|size| size.parse().ok()) {
+        return Some(size);
+    }
+    let output = std::process::Command::new("tput").arg(capability).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Explain the statement of the selected YSQL session, and show the plan. EXPLAIN ANALYZE runs the
+/// statement, so it asks first.
+fn explain(screen: &mut Screen, login: &Login, commands: &Receiver<String>, analyze: bool) {
+    let row = match screen.selected_row() {
+        Some(tracked) if tracked.row.api == "YSQL" && !tracked.row.query.is_empty() => tracked.row.clone(),
+        Some(_) => {
+            screen.message = Some(String::from("only the statements of YSQL sessions can be explained"));
+            return;
+        }
+        None => {
+            screen.message = Some(String::from("select a YSQL session with j and k first"));
+            return;
+        }
+    };
+    if let Some(parameter) = ysql::bind_parameter(&row.query) {
+        screen.message = Some(format!(
+            "the statement has bind parameters, such as {}, of which /rpcz doesn't show the values, so it can't be explained",
+            parameter
+        ));
+        return;
+    }
+    if analyze && !commands::confirm(commands, "EXPLAIN ANALYZE runs the statement, in a transaction that is rolled back") {
+        screen.message = Some(String::from("did not explain analyze"));
+        return;
+    }
+    let database = ysql::database(&row);
+    match ysql::explain(login, &row.server, &database, &row.query, analyze) {
+        Ok(plan) => {
+            let mut lines: Vec<String> = detail::pretty_print(&row.query).lines().map(syntax::printable).collect();
+            lines.push(String::new());
+            // the plan shows the literals of the query.
+            lines.extend(plan.iter().map(|line| syntax::printable(line)));
+            screen.plan = Some(Pane {
+                title: format!("{} on {} in {}", if analyze { "EXPLAIN ANALYZE" } else { "EXPLAIN" }, row.server, database),
+                lines,
+                offset: 0,
+            });
+            screen.view = View::Plan;
+        }
+        Err(e) => screen.message = Some(e.to_string()),
+    }
+}
+
+fn update(screen: &mut Screen, snapshot: &ClusterSnapshot, lock_collector: &mut Option<LockCollector>) {
+    screen.update(snapshot).unwrap_or_else(|e| {
+        eprintln!("Error writing slow query log: {}", e);
+        process::exit(1);
+    });
+    if let Some(lock_collector) = lock_collector {
+        (screen.locks, screen.lock_errors) = lock_collector.collect(&locks::ysql_servers(snapshot));
+        screen.locks_collected = true;
+    }
+}
+
+/// What is shown of the refreshes: the view, and what the trackers make of the snapshots.
+struct Screen {
+    view: View,
+    idle: bool,
+    tracker: SessionTracker,
+    rows: Vec<TrackedRow>,
+    slow_queries: SlowQueryLog,
+    throughput_tracker: ThroughputTracker,
+    throughput: Vec<ConnectionThroughput>,
+    skew_tracker: SkewTracker,
+    skew: Vec<ApiSkew>,
+    skew_refreshes: usize,
+    load: LoadTracker,
+    ash: AshTracker,
+    /// the sessions and slow queries that are shown match all filters.
+    filters: Vec<Filter>,
+    /// the matches in the queries are highlighted.
+    search: Option<Search>,
+    /// the row that was jumped to, marked in bold, and shown in the detail view.
+    selected: Option<RowKey>,
+    /// the queries are cut off at the width of the terminal.
+    width: Option<usize>,
+    /// the plan is shown a page of the height of the terminal at a time.
+    height: Option<usize>,
+    /// the plan of the statement of the selected session, shown in the plan view.
+    plan: Option<Pane>,
+    /// the backends in blocking chains, when the locks are collected, and the servers that couldn't tell.
+    locks: Vec<BlockedBackend>,
+    lock_errors: Vec<String>,
+    locks_collected: bool,
+    /// the servers of which pg_stat_activity couldn't be read.
+    activity_errors: Vec<String>,
+    /// the queries, statuses and times are colored, the times by the time colors.
+    color: bool,
+    time_colors: Thresholds,
+    /// shown until the next command.
+    message: Option<String>,
+}
+
+impl Screen {
+    fn new(idle: bool, slow_queries: SlowQueryLog, skew_tracker: SkewTracker) -> Self {
+        Screen {
+            view: View::Sessions,
+            idle,
+            tracker: SessionTracker::new(),
+            rows: Vec::new(),
+            slow_queries,
+            throughput_tracker: ThroughputTracker::new(),
+            throughput: Vec::new(),
+            skew_tracker,
+            skew: Vec::new(),
+            skew_refreshes: 5,
+            load: LoadTracker::new(),
+            ash: AshTracker::new(),
+            filters: Vec::new(),
+            search: None,
+            selected: None,
+            width: None,
+            height: None,
+            plan: None,
+            locks: Vec::new(),
+            lock_errors: Vec::new(),
+            locks_collected: false,
+            activity_errors: Vec::new(),
+            color: false,
+            time_colors: Thresholds::default(),
+            message: None,
+        }
+    }
+
+    /// The rows that are shown: those that match the filters, and the search when it hides the others.
+    fn visible_rows(&self) -> Vec<TrackedRow> {
+        self.rows
+            .iter()
+            .filter(|tracked| filter::matches_all(&self.filters, &tracked.row))
+            .filter(|tracked| match &self.search {
+                Some(search) if search.hide => search.matches(&tracked.row),
+                _ => true,
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// The lines of a pane that fit on the terminal, under the status line, the title and the footer of the pane.
+    fn page(&self) -> Option<usize> {
+        self.height.map(|height| height.saturating_sub(4).max(1))
+    }
+
+    /// Show the next page of the plan, or the previous one.
+    fn scroll(&mut self, forward: bool) {
+        let page = self.page();
+        if let Some(plan) = &mut self.plan {
+            let page = page.unwrap_or(plan.lines.len()).max(1);
+            plan.offset = if forward {
+                (plan.offset + page).min(plan.lines.len().saturating_sub(page))
+            } else {
+                plan.offset.saturating_sub(page)
+            };
+        }
+    }
+
+    fn selected_row(&self) -> Option<&TrackedRow> {
+        let selected = self.selected.as_ref()?;
+        self.rows.iter().find(|tracked| tracking::row_key(&tracked.row) == *selected)
+    }
+
+    /// Select the next shown row, or the previous one, starting over at the other end.
+    fn select_row(&mut self, forward: bool) {
+        let keys: Vec<RowKey> = self.visible_rows().iter().map(|tracked| tracking::row_key(&tracked.row)).collect();
+        if keys.is_empty() {
+            self.selected = None;
+            return;
+        }
+        let position = self.selected.as_ref().and_then(|selected| keys.iter().position(|key| key == selected));
+        let next = match (position, forward) {
+            (Some(position), true) => (position + 1) % keys.len(),
+            (Some(position), false) => (position + keys.len() - 1) % keys.len(),
+            (None, true) => 0,
+            (None, false) => keys.len() - 1,
+        };
+        self.selected = Some(keys[next].clone());
+    }
+
+    /// Select the next shown row that matches the search, or the previous one.
+    fn select_match(&mut self, forward: bool) {
+        if let Some(search) = &self.search {
+            self.selected = search::next_match(&self.visible_rows(), self.selected.as_ref(), search, forward);
+            if self.selected.is_none() {
+                self.message = Some(format!("no sessions match /{}/", search.pattern()));
+            }
+        }
+    }
+
+    /// Track a new snapshot.
+    fn update(&mut self, snapshot: &ClusterSnapshot) -> io::Result<()> {
+        self.rows = self.tracker.update(snapshot, self.idle);
+        self.throughput = self.throughput_tracker.update(snapshot);
+        self.skew = self.skew_tracker.update(snapshot);
+        self.load.update(snapshot);
+        self.ash.update(snapshot);
+        self.slow_queries.add(snapshot.timestamp, &self.rows)
+    }
+}
+
+/// A text that can be longer than the terminal, shown a page at a time.
+struct Pane {
+    title: String,
+    lines: Vec<String>,
+    /// the first line that is shown.
+    offset: usize,
+}
+
+/// Write the page of a pane from its offset, or without a page, all of it.
+fn write_pane(out: &mut dyn Write, pane: &Pane, page: Option<usize>) -> io::Result<()> {
+    writeln!(out, "{}", pane.title)?;
+    let end = page.map_or(pane.lines.len(), |page| (pane.offset + page).min(pane.lines.len()));
+    for line in &pane.lines[pane.offset.min(end)..end] {
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out, "lines {}-{} of {}  f:forward b:back e:back to the session", pane.offset + 1, end, pane.lines.len())
+}
+
+fn render(out: &mut dyn Write, snapshot: &ClusterSnapshot, screen: &Screen, masters: &[String]) -> io::Result<()> {
+    if let (View::Plan, Some(plan)) = (screen.view, &screen.plan) {
+        return write_pane(out, plan, screen.page());
+    }
+    load::write_load(out, &screen.load, SPARKLINE_REFRESHES)?;
+    if !screen.filters.is_empty() {
+        let filters: Vec<String> = screen.filters.iter().map(|f| f.to_string()).collect();
+        writeln!(out, "filter: {}", filters.join(", "))?;
+    }
+    if let Some(search) = &screen.search {
+        writeln!(out, "search: /{}/{}", search.pattern(), if search.hide { " matches only" } else { "" })?;
+    }
+    if let Some(message) = &screen.message {
+        writeln!(out, "{}", message)?;
+    }
+    write_endpoints(out, &snapshot.endpoints)?;
+    skew::write_skew(out, &screen.skew, screen.skew_refreshes)?;
+    for error in screen.activity_errors.iter().chain(&screen.lock_errors) {
+        writeln!(out, "{}", error)?;
+    }
+    for (root, waiters) in locks::root_blockers(&screen.locks) {
+        writeln!(
+            out,
+            "BLOCKING: backend {} of {} on {} blocks {} sessions, l shows the chains",
+            root.pid, root.client, root.server, waiters
+        )?;
+    }
+    writeln!(out)?;
+    match screen.view {
+        View::Throughput => return throughput::write_throughput(out, &screen.throughput),
+        View::Inventory => return inventory::write_inventory(out, &inventory::connection_counts(snapshot)),
+        View::Locks if !screen.locks_collected => return writeln!(out, "Start ybtop with --locks to read the locks"),
+        View::Locks => return locks::write_chains(out, &screen.locks, &screen.rows, screen.color),
+        View::Ash => return ash::write_ash(out, &screen.ash, ASH_FINGERPRINTS),
+        View::Detail => {
+            return match screen.selected_row() {
+                Some(tracked) => {
+                    let hot_tables = match &snapshot.entities {
+                        Some(entities) => tablets::hot_tables(&snapshot.tserver_calls(), entities),
+                        None => Vec::new(),
+                    };
+                    let raw = snapshot.raw_fields(&tracked.row);
+                    let extra = snapshot.extra_fields(&tracked.row);
+                    detail::write_detail(out, tracked, raw.as_ref(), extra.as_ref(), &hot_tables, screen.color)
+                }
+                None => writeln!(out, "No session is selected, select one with j and k"),
+            };
+        }
+        // the plan view without a plan shows the sessions.
+        View::Sessions | View::Plan => {}
+    }
+    let format = RowFormat {
+        search: screen.search.as_ref(),
+        selected: screen.selected.as_ref(),
+        width: screen.width,
+        colors: if screen.color { Some(screen.time_colors) } else { None },
+        locks: &screen.locks,
+    };
+    write_sessions(out, &screen.visible_rows(), &format)?;
+    slowlog::write_slow_queries(out, &screen.slow_queries, &screen.filters)?;
+    let connection_manager_stats = snapshot.connection_manager_stats();
+    if !connection_manager_stats.is_empty() {
+        connmgr::write_pools(out, &connection_manager_stats)?;
+    }
+    if !masters.is_empty() {
+        match &snapshot.entities {
+            Some(entities) => tablets::write_hotspots(out, &snapshot.tserver_calls(), entities)?,
+            None => writeln!(out, "No master responded to /dump-entities on: {}", masters.join(","))?,
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn write_endpoints(out: &mut dyn Write, endpoints: &[EndpointSnapshot]) -> io::Result<()> {
+    writeln!(out, "{:26} {:16} notes", "endpoint", "version")?;
+    for endpoint_snapshot in endpoints {
+        let mut notes = Vec::new();
+        if let Some(error) = &endpoint_snapshot.error {
+            notes.push(error.clone());
+        }
+        if !endpoint_snapshot.missing_fields.is_empty() {
+            notes.push(format!(
+                "missing fields: {}",
+                endpoint_snapshot.missing_fields.iter().cloned().collect::<Vec<String>>().join(",")
+            ));
+        }
+        let unknown = endpoint_snapshot.unknown_fields();
+        if !unknown.is_empty() {
+            notes.push(format!("unknown fields: {}", unknown.into_iter().collect::<Vec<String>>().join(",")));
+        }
+        writeln!(
+            out,
+            "{:26} {:16} {}",
+            endpoint_snapshot.endpoint.to_string(),
+            endpoint_snapshot.version.as_deref().unwrap_or("unknown"),
+            notes.join(" ")
+        )?;
+    }
+    Ok(())
+}
+
+/// How the session rows are written.
+#[derive(Default)]
+pub(crate) struct RowFormat<'a> {
+    /// the matches of this search are highlighted.
+    pub search: Option<&'a Search>,
+    /// this row is in bold.
+    pub selected: Option<&'a RowKey>,
+    /// the queries, always on one line, are cut off at this width.
+    pub width: Option<usize>,
+    /// the queries, the statuses and the times are colored, the times by these thresholds.
+    pub colors: Option<Thresholds>,
+    /// the state of the YSQL sessions in these blocking chains is block for a root blocker, and wait for the others.
+    pub locks: &'a [BlockedBackend],
+}
+
+pub(crate) fn write_sessions(out: &mut dyn Write, rows: &[TrackedRow], format: &RowFormat) -> io::Result<()> {
+    // the wait column is there when the wait events are known.
+    let waits = rows.iter().any(|tracked| tracked.row.wait.is_some());
+    writeln!(
+        out,
+        "{:4} {:5} {:20} {:20} {:10} {:10} {}{:>8} {:>6} query",
+        "API",
+        "state",
+        "server",
+        "client",
+        "key/db",
+        "status",
+        if waits { format!("{:20} ", "wait") } else { String::new() },
+        "time_s",
+        "stmt/s"
+    )?;
+    for tracked in rows {
+        let row = &tracked.row;
+        let mut state = format!("{:5}", tracked.state.label());
+        let mut status = format!("{:10}", row.status);
+        let mut wait = row.wait.as_deref().unwrap_or_default();
+        if let Some(backend) = locks::backend_of(format.locks, row) {
+            state = if backend.is_root() { String::from("block") } else { String::from("wait ") };
+            if format.colors.is_some() && backend.is_root() {
+                state = severity::styled(&state, Some(locks::ROOT_STYLE));
+            }
+            // a session blocked in a chain waits on a lock, also when its wait event isn't known.
+            if !backend.is_root() && wait.is_empty() {
+                wait = "Lock";
+            }
+        }
+        let mut time = format!("{:8.3}", row.query_time_ms as f32 / 1000.0);
+        if let Some(thresholds) = &format.colors {
+            status = severity::styled(&status, severity::status_style(&row.api, &row.status, wait));
+            time = severity::styled(&time, severity::time_style(thresholds, row.query_time_ms));
+        }
+        let columns = format!(
+            "{:4} {} {:20} {:20} {:10} {} {}{} {:>6} ",
+            row.api,
+            state,
+            row.server,
+            row.client,
+            row.keyspace_db_name,
+            status,
+            if waits { format!("{:20} ", ash::wait_label(row)) } else { String::new() },
+            time,
+            tracked.statements_per_second.map(|rate| format!("{:.1}", rate)).unwrap_or_default()
+        );
+        let query = match format.search {
+            Some(search) => search.text(row),
+            None => row.query.clone(),
+        };
+        let query = syntax::one_line(&query);
+        let query = match format.width {
+            Some(width) => truncate(&query, width.saturating_sub(syntax::visible_width(&columns))),
+            None => query,
+        };
+        let mut spans = if format.colors.is_some() { syntax::spans(&query) } else { Vec::new() };
+        if let Some(search) = format.search {
+            spans.extend(search.spans(&query));
+        }
+        let query = syntax::paint(&query, &spans);
+        let (bold, normal) = if format.selected == Some(&tracking::row_key(row)) { ("\x1b[1m", "\x1b[22m") } else { ("", "") };
+        writeln!(out, "{}{}{}{}", bold, columns, query, normal)?;
+    }
+    Ok(())
+}
+
+/// A line cut off with an ellipsis when it is wider than width.
+fn truncate(line: &str, width: usize) -> String {
+    if line.chars().count() <= width {
+        return line.to_string();
+    }
+    let mut cut: String = line.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::source::ScriptedSource;
+
+    #[test]
+    fn render_scripted_cluster() {
+        let ysql = Endpoint::new("192.168.66.80", "13000");
+        let ycql = Endpoint::new("192.168.66.81", "12000");
+        let mut source = ScriptedSource::new();
+        source.push(&ysql, "/rpcz", Ok(String::from(r#"{"connections": [{"process_start_time": "2022-03-27 15:55:22.587029+00", "application_name": "ysqlsh", "backend_type": "client backend", "backend_status": "active", "db_name": "yugabyte", "host": "127.0.0.1", "port": "50736", "query": "select pg_sleep(120);", "query_running_for_ms": 26853}]}"#)));
+        source.push(&ycql, "/rpcz", Err(Error::Timeout(ycql.to_string())));
+        let mut collector = Collector::with_source(vec![ysql, ycql], Box::new(source));
+        let snapshot = collector.collect().unwrap();
+        let mut out = Vec::new();
+        let mut screen = Screen::new(false, SlowQueryLog::new(Duration::from_secs(10), 10), SkewTracker::new(2.0));
+        screen.update(&snapshot).unwrap();
+        render(&mut out, &snapshot, &screen, &[]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "active sessions 1/5/15m: YSQL 1.00 1.00 1.00  YCQL 0.00 0.00 0.00  █");
+        assert_eq!(lines[2].trim_end(), "192.168.66.80:13000        unknown");
+        assert_eq!(lines[3], "192.168.66.81:12000        unknown          192.168.66.81:12000 timed out");
+        assert_eq!(lines[6], "YSQL       192.168.66.80        127.0.0.1:50736      yugabyte   active       26.853        select pg_sleep(120);");
+        assert_eq!(lines.len(), 7);
+
+        // cut off at the width of the terminal, and everything in the detail view.
+        screen.width = Some(110);
+        let mut out = Vec::new();
+        render(&mut out, &snapshot, &screen, &[]).unwrap();
+        assert!(String::from_utf8(out).unwrap().lines().nth(6).unwrap().ends_with("26.853        select pg_sleep(12…"));
+        // in color, the time over 10 seconds is red.
+        screen.color = true;
+        let mut out = Vec::new();
+        render(&mut out, &snapshot, &screen, &[]).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("\x1b[32mactive    \x1b[39m \x1b[31m  26.853\x1b[39m"));
+        screen.color = false;
+        screen.view = View::Detail;
+        screen.select_row(true);
+        let mut out = Vec::new();
+        render(&mut out, &snapshot, &screen, &[]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("YSQL session on 192.168.66.80 from 127.0.0.1:50736\n"));
+        assert!(out.contains("query:\n  select pg_sleep(120);\n"));
+        assert!(out.contains("\n  application_name: ysqlsh\n"));
+    }
+
+    #[test]
+    fn query_on_one_line() {
+        let ysql = Endpoint::new("192.168.66.80", "13000");
+        let mut source = ScriptedSource::new();
+        source.push(&ysql, "/rpcz", Ok(String::from(r#"{"connections": [{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_status": "active", "db_name": "yugabyte", "host": "127.0.0.1", "port": "50736", "query": "select 1\n  from t\twhere a = '\u001b[2J'", "query_running_for_ms": 1200}]}"#)));
+        let snapshot = Collector::with_source(vec![ysql], Box::new(source)).collect().unwrap();
+        let mut screen = Screen::new(false, SlowQueryLog::new(Duration::from_secs(10), 10), SkewTracker::new(2.0));
+        screen.update(&snapshot).unwrap();
+        // also when the width of the terminal isn't known.
+        let mut out = Vec::new();
+        render(&mut out, &snapshot, &screen, &[]).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("select 1 from t where a = '\\u{1b}[2J'\n"));
+        // and escaped in the detail view, also in color.
+        screen.view = View::Detail;
+        screen.select_row(true);
+        for color in [false, true] {
+            screen.color = color;
+            let mut out = Vec::new();
+            render(&mut out, &snapshot, &screen, &[]).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            assert!(!out.contains("\x1b[2J"));
+            assert!(out.contains("\n  query: select 1\\n  from t\\twhere a = '\\u{1b}[2J'\n"));
+        }
+    }
+
+    #[test]
+    fn mark_root_blocker() {
+        let ysql = Endpoint::new("192.168.66.80", "13000");
+        let mut source = ScriptedSource::new();
+        source.push(&ysql, "/rpcz", Ok(String::from(r#"{"connections": [{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_status": "idle in transaction", "db_name": "yugabyte", "host": "127.0.0.1", "port": "50736", "query": "update t set a = 1 where id = 1;", "query_running_for_ms": 1200}]}"#)));
+        let snapshot = Collector::with_source(vec![ysql], Box::new(source)).collect().unwrap();
+        let mut screen = Screen::new(false, SlowQueryLog::new(Duration::from_secs(10), 10), SkewTracker::new(2.0));
+        screen.update(&snapshot).unwrap();
+        let backend = |pid, client: &str, blocked_by: Vec<i32>| BlockedBackend {
+            server: String::from("192.168.66.80"),
+            pid,
+            client: client.to_string(),
+            state: String::from("active"),
+            query: String::from("update t set a = 2 where id = 1"),
+            transaction_seconds: 1.0,
+            blocked_by,
+        };
+        screen.locks = vec![backend(4242, "127.0.0.1:50736", Vec::new()), backend(4243, "127.0.0.1:50737", vec![4242])];
+        screen.locks_collected = true;
+        let mut out = Vec::new();
+        render(&mut out, &snapshot, &screen, &[]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\nBLOCKING: backend 4242 of 127.0.0.1:50736 on 192.168.66.80 blocks 1 sessions, l shows the chains\n"));
+        assert!(out.contains("\nYSQL block 192.168.66.80        127.0.0.1:50736 "));
+        screen.view = View::Locks;
+        let mut out = Vec::new();
+        render(&mut out, &snapshot, &screen, &[]).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("\n└ 4243 "));
+    }
+
+    #[test]
+    fn scroll_plan() {
+        let mut screen = Screen::new(false, SlowQueryLog::new(Duration::from_secs(10), 10), SkewTracker::new(2.0));
+        screen.height = Some(7);
+        screen.plan = Some(Pane {
+            title: String::from("EXPLAIN on 192.168.66.80 in yugabyte"),
+            lines: (1..=8).map(|n| format!("line {}", n)).collect(),
+            offset: 0,
+        });
+        screen.scroll(true);
+        screen.scroll(true);
+        let mut out = Vec::new();
+        write_pane(&mut out, screen.plan.as_ref().unwrap(), screen.page()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "EXPLAIN on 192.168.66.80 in yugabyte\nline 6\nline 7\nline 8\nlines 6-8 of 8  f:forward b:back e:back to the session\n"
+        );
+        screen.scroll(false);
+        assert_eq!(screen.plan.as_ref().unwrap().offset, 2);
+    }
+}
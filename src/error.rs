@@ -0,0 +1,69 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    /// collect was asked for a snapshot without endpoints.
+    NoEndpoints,
+    /// the endpoint's port doesn't accept connections.
+    NotReachable(String),
+    /// the endpoint didn't answer in time.
+    Timeout(String),
+    Http(reqwest::Error),
+    Json(serde_json::Error),
+    Io(io::Error),
+    /// reading or writing the history database.
+    History(rusqlite::Error),
+    /// talking to a YSQL server over the PostgreSQL protocol.
+    Ysql(postgres::Error),
+    /// an error that was recorded, as it was shown.
+    Recorded(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoEndpoints => write!(f, "no endpoints to collect from"),
+            Error::NotReachable(endpoint) => write!(f, "{} not reachable", endpoint),
+            Error::Timeout(endpoint) => write!(f, "{} timed out", endpoint),
+            Error::Http(e) => write!(f, "error reading: {}", e),
+            Error::Json(e) => write!(f, "error parsing json data: {}", e),
+            Error::Io(e) => write!(f, "error writing: {}", e),
+            Error::History(e) => write!(f, "error in history database: {}", e),
+            Error::Ysql(e) => write!(f, "error in YSQL: {}", e),
+            Error::Recorded(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::History(e)
+    }
+}
+
+impl From<postgres::Error> for Error {
+    fn from(e: postgres::Error) -> Self {
+        Error::Ysql(e)
+    }
+}
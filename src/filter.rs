@@ -0,0 +1,198 @@
+//! Filters on the columns of the session list, such as api=YCQL, db=orders, client~10.1.,
+//! time>5s or status!=QUERY. A row is shown when it matches all filters.
+use crate::snapshot::GeneralPresentation;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Api,
+    Server,
+    Client,
+    Db,
+    App,
+    Status,
+    Time,
+    Query,
+    /// from pg_stat_activity, for the YSQL sessions when it is read.
+    User,
+    Pid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Contains,
+    NotContains,
+    Greater,
+    Less,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    field: Field,
+    operator: Operator,
+    /// lowercase, for the text fields.
+    value: String,
+    /// for time.
+    millis: u32,
+    text: String,
+}
+
+/// The operators, the ones that start with another operator first.
+const OPERATORS: [(&str, Operator); 6] = [
+    ("!=", Operator::NotEqual),
+    ("!~", Operator::NotContains),
+    ("=", Operator::Equal),
+    ("~", Operator::Contains),
+    (">", Operator::Greater),
+    ("<", Operator::Less),
+];
+
+/// Parse a filter: a field, an operator and a value. The fields are api, server, client, db
+/// (or keyspace), app, status, time and query, and user and pid when pg_stat_activity is read. The operators are = and != for equal, ~ and !~
+/// for contains, both ignoring case, and > and < for time, in ms, s or m, or without unit, in seconds.
+pub fn parse_filter(text: &str) -> Result<Filter, String> {
+    let text = text.trim();
+    let (position, length, operator) = OPERATORS
+        .iter()
+        .filter_map(|(symbol, operator)| text.find(symbol).map(|position| (position, symbol.len(), *operator)))
+        .min_by_key(|(position, length, _)| (*position, std::cmp::Reverse(*length)))
+        .ok_or_else(|| format!("no operator in filter: {}", text))?;
+    let name = text[..position].trim().to_lowercase();
+    let value = text[position + length..].trim();
+    let field = match name.as_str() {
+        "api" => Field::Api,
+        "server" => Field::Server,
+        "client" => Field::Client,
+        "db" | "keyspace" | "key" => Field::Db,
+        "app" | "application" => Field::App,
+        "status" => Field::Status,
+        "time" => Field::Time,
+        "query" => Field::Query,
+        "user" => Field::User,
+        "pid" => Field::Pid,
+        _ => return Err(format!("unknown field in filter: {}", text)),
+    };
+    let millis = match (field, operator) {
+        (Field::Time, Operator::Greater | Operator::Less) => parse_millis(value).ok_or_else(|| format!("invalid time in filter: {}", text))?,
+        (Field::Time, _) | (_, Operator::Greater | Operator::Less) => {
+            return Err(format!("time can only be compared with > and <: {}", text))
+        }
+        _ => 0,
+    };
+    Ok(Filter {
+        field,
+        operator,
+        value: value.to_lowercase(),
+        millis,
+        text: format!("{}{}{}", name, &text[position..position + length], value),
+    })
+}
+
+/// Parse filters separated by commas.
+pub fn parse_filters(text: &str) -> Result<Vec<Filter>, String> {
+    text.split(',').filter(|f| !f.trim().is_empty()).map(parse_filter).collect()
+}
+
+fn parse_millis(value: &str) -> Option<u32> {
+    let (number, factor) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 1000.0)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 60000.0)
+    } else {
+        (value, 1000.0)
+    };
+    number.trim().parse::<f64>().ok().map(|number| (number * factor) as u32)
+}
+
+impl Filter {
+    pub fn matches(&self, row: &GeneralPresentation) -> bool {
+        let pid;
+        let field: &str = match self.field {
+            Field::Api => &row.api,
+            Field::Server => &row.server,
+            Field::Client => &row.client,
+            Field::Db => &row.keyspace_db_name,
+            Field::App => &row.application,
+            Field::Status => &row.status,
+            Field::Query => &row.query,
+            Field::User => row.activity.as_ref().map_or("", |a| a.user.as_str()),
+            Field::Pid => {
+                pid = row.activity.as_ref().map(|a| a.pid.to_string()).unwrap_or_default();
+                &pid
+            }
+            Field::Time => {
+                return match self.operator {
+                    Operator::Greater => row.query_time_ms > self.millis,
+                    _ => row.query_time_ms < self.millis,
+                }
+            }
+        };
+        let field = field.to_lowercase();
+        match self.operator {
+            Operator::Equal => field == self.value,
+            Operator::NotEqual => field != self.value,
+            Operator::Contains => field.contains(&self.value),
+            Operator::NotContains => !field.contains(&self.value),
+            Operator::Greater | Operator::Less => true,
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Whether a row matches all filters.
+pub fn matches_all(filters: &[Filter], row: &GeneralPresentation) -> bool {
+    filters.iter().all(|filter| filter.matches(row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row() -> GeneralPresentation {
+        GeneralPresentation {
+            api: String::from("YCQL"),
+            server: String::from("192.168.66.80"),
+            client: String::from("10.1.0.5:35518"),
+            keyspace_db_name: String::from("orders"),
+            status: String::from("QUERY"),
+            query_time_ms: 7466,
+            query: String::from("select * from orders"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn filter_rows() {
+        let row = row();
+        for (filters, matches) in [
+            ("api=YCQL", true),
+            ("api=ycql, db=orders", true),
+            ("client~10.1.", true),
+            ("client !~ 10.1.", false),
+            ("time>5s", true),
+            ("time>7.5", false),
+            ("time<8000ms", true),
+            ("status!=QUERY", false),
+            ("keyspace=orders, time>1m", false),
+            ("user=yugabyte", false),
+            ("pid!=4242", true),
+        ] {
+            let filters = parse_filters(filters).unwrap();
+            assert_eq!(matches_all(&filters, &row), matches, "{:?}", filters);
+        }
+        assert_eq!(parse_filter("client !~ 10.1.").unwrap().to_string(), "client!~10.1.");
+        assert!(parse_filter("api>YCQL").is_err());
+        assert!(parse_filter("time=5").is_err());
+        assert!(parse_filter("color=red").is_err());
+        assert!(parse_filter("api").is_err());
+    }
+}
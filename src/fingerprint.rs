@@ -0,0 +1,69 @@
+//! Query fingerprints: the query text with its literals replaced, so executions of the same
+//! statement with different values count as one.
+
+/// Lowercase the query, replace string and number literals and bind markers by ?,
+/// collapse lists of ? into one, and collapse whitespace.
+pub fn fingerprint(query: &str) -> String {
+    let mut normalized = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // a quote in a string literal is written as two quotes.
+                while let Some(c) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                normalized.push('?');
+            }
+            '$' if chars.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    chars.next();
+                }
+                normalized.push('?');
+            }
+            c if c.is_ascii_digit() && !normalized.ends_with(|p: char| p.is_alphanumeric() || p == '_') => {
+                while chars.peek().is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+                    chars.next();
+                }
+                normalized.push('?');
+            }
+            c if c.is_whitespace() => {
+                if !normalized.is_empty() && !normalized.ends_with(' ') {
+                    normalized.push(' ');
+                }
+            }
+            c => normalized.extend(c.to_lowercase()),
+        }
+    }
+    let mut normalized = normalized.trim_end().to_string();
+    while normalized.contains("?, ?") || normalized.contains("?,?") {
+        normalized = normalized.replace("?, ?", "?").replace("?,?", "?");
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_replaces_literals() {
+        assert_eq!(
+            fingerprint("SELECT * FROM orders WHERE id = 42 AND name = 'O''Brien'"),
+            "select * from orders where id = ? and name = ?"
+        );
+        assert_eq!(fingerprint("select pg_sleep(120);"), "select pg_sleep(?);");
+        assert_eq!(fingerprint("select * from t2 where a in (1, 2,3)"), "select * from t2 where a in (?)");
+        assert_eq!(fingerprint("update  t\n set a = $1\twhere b = $2"), "update t set a = ? where b = ?");
+        assert_eq!(
+            fingerprint("INSERT INTO cr.fa_bg_checks (year_month, state) VALUES (?, ?)"),
+            "insert into cr.fa_bg_checks (year_month, state) values (?)"
+        );
+    }
+}
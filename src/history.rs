@@ -0,0 +1,378 @@
+//! The history of the sessions, kept in a SQLite database so weeks of activity can be queried later.
+//!
+//! The database has two tables. The times are in milliseconds since the epoch.
+//!
+//! ```sql
+//! -- a row per endpoint per refresh, also when the endpoint didn't answer.
+//! create table samples (
+//!     sample_time integer not null,
+//!     endpoint    text not null,   -- host:port
+//!     version     text,
+//!     error       text,            -- why the endpoint had no sessions, such as 'not reachable'
+//!     interval_ms integer          -- the time since the previous refresh, at most the refresh interval,
+//!                                  -- null for the first one of a run
+//! );
+//! -- a row per session that was not idle, per refresh: the rows ybtop shows.
+//! create table sessions (
+//!     sample_time      integer not null,
+//!     endpoint         text not null,
+//!     api              text not null,  -- YSQL, YCQL or YEDS
+//!     server           text not null,
+//!     client           text not null,  -- host:port, the host can be an IPv6 address
+//!     keyspace_db_name text not null,
+//!     status           text not null,
+//!     query_time_ms    integer not null,
+//!     query            text not null,
+//!     fingerprint      text not null   -- the query with its literals replaced by ?
+//! );
+//! ```
+use crate::error::Error;
+use crate::fingerprint::fingerprint;
+use crate::snapshot::ClusterSnapshot;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use rusqlite::{params, Connection};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+create table if not exists samples (
+    sample_time integer not null,
+    endpoint    text not null,
+    version     text,
+    error       text,
+    interval_ms integer
+);
+create table if not exists sessions (
+    sample_time      integer not null,
+    endpoint         text not null,
+    api              text not null,
+    server           text not null,
+    client           text not null,
+    keyspace_db_name text not null,
+    status           text not null,
+    query_time_ms    integer not null,
+    query            text not null,
+    fingerprint      text not null
+);
+create index if not exists sessions_sample_time on sessions (sample_time);
+create index if not exists samples_sample_time on samples (sample_time);
+";
+
+/// The client host: the client without the port after its last colon, as the host of an IPv6
+/// client has colons too.
+const CLIENT_HOST: &str = "case when client glob '*:[0-9]*' and rtrim(client, '0123456789') like '%:'
+    then substr(client, 1, length(rtrim(client, '0123456789')) - 1) else client end";
+
+/// The sessions with the interval of their sample, the time they are taken to have run since the
+/// previous refresh.
+const SAMPLED_SESSIONS: &str = "sessions join samples using (sample_time, endpoint)";
+
+pub struct History {
+    connection: Connection,
+    previous: Option<SystemTime>,
+    /// the refresh interval, the most a sample counts.
+    refresh: Duration,
+}
+
+/// A statement in the history, over the samples in which a session was running it.
+#[derive(Debug)]
+pub struct StatementHistory {
+    pub api: String,
+    pub fingerprint: String,
+    pub samples: u64,
+    /// the refresh intervals of the samples added up, per session running it.
+    pub sampled_time_ms: u64,
+    pub max_time_ms: u64,
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+}
+
+/// A client host in the history, over the samples in which it had a long running session.
+#[derive(Debug)]
+pub struct ClientHistory {
+    pub api: String,
+    pub client_host: String,
+    /// the number of different client ports.
+    pub sessions: u64,
+    pub samples: u64,
+    /// the refresh intervals of the samples added up, per long running session.
+    pub sampled_time_ms: u64,
+    pub max_time_ms: u64,
+    pub last_seen: SystemTime,
+}
+
+impl History {
+    /// Open the database, and create the tables if it doesn't have them.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        History::with_connection(Connection::open(path)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, Error> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(History {
+            connection,
+            previous: None,
+            refresh: Duration::MAX,
+        })
+    }
+
+    /// Count a sample for at most the refresh interval, so a pause or a stalled refresh isn't taken as
+    /// the sessions of the next refresh running all that time.
+    pub fn with_refresh_interval(mut self, refresh: Duration) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Add the endpoints and the sessions that are not idle of a snapshot.
+    pub fn write(&mut self, snapshot: &ClusterSnapshot) -> Result<(), Error> {
+        let sample_time = millis(snapshot.timestamp);
+        let interval_ms = self
+            .previous
+            .and_then(|previous| snapshot.sampled_since(previous, self.refresh))
+            .map(|interval| interval.as_millis() as i64);
+        self.previous = Some(snapshot.timestamp);
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert_sample = transaction
+                .prepare("insert into samples (sample_time, endpoint, version, error, interval_ms) values (?1, ?2, ?3, ?4, ?5)")?;
+            let mut insert_session = transaction.prepare(
+                "insert into sessions (sample_time, endpoint, api, server, client, keyspace_db_name, status, query_time_ms, query, fingerprint)
+                 values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            for endpoint_snapshot in &snapshot.endpoints {
+                let endpoint = endpoint_snapshot.endpoint.to_string();
+                insert_sample.execute(params![sample_time, endpoint, endpoint_snapshot.version, endpoint_snapshot.error, interval_ms])?;
+                for row in endpoint_snapshot.sessions(false) {
+                    insert_session.execute(params![
+                        sample_time,
+                        endpoint,
+                        row.api,
+                        row.server,
+                        row.client,
+                        row.keyspace_db_name,
+                        row.status,
+                        row.query_time_ms,
+                        row.query,
+                        fingerprint(&row.query)
+                    ])?;
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// The statements with the most sampled time between from and to.
+    pub fn top_statements(&self, from: SystemTime, to: SystemTime, limit: u32) -> Result<Vec<StatementHistory>, Error> {
+        let mut statement = self.connection.prepare(&format!(
+            "select api, fingerprint, count(*), coalesce(sum(interval_ms), 0), max(query_time_ms), min(sample_time), max(sample_time)
+             from {}
+             where sample_time >= ?1 and sample_time < ?2
+             group by api, fingerprint
+             order by sum(interval_ms) desc, count(*) desc, max(query_time_ms) desc
+             limit ?3",
+            SAMPLED_SESSIONS
+        ))?;
+        let rows = statement.query_map(params![millis(from), millis(to), limit], |row| {
+            Ok(StatementHistory {
+                api: row.get(0)?,
+                fingerprint: row.get(1)?,
+                samples: row.get::<_, i64>(2)? as u64,
+                sampled_time_ms: row.get::<_, i64>(3)? as u64,
+                max_time_ms: row.get::<_, i64>(4)? as u64,
+                first_seen: time(row.get(5)?),
+                last_seen: time(row.get(6)?),
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// The client hosts that had sessions running for at least min_time between from and to, the longest first.
+    pub fn long_running_clients(&self, from: SystemTime, to: SystemTime, min_time: Duration) -> Result<Vec<ClientHistory>, Error> {
+        let mut statement = self.connection.prepare(&format!(
+            "select api, client_host, count(distinct client), count(*), coalesce(sum(interval_ms), 0), max(query_time_ms), max(sample_time)
+             from (select *, {} as client_host from {})
+             where sample_time >= ?1 and sample_time < ?2 and query_time_ms >= ?3
+             group by api, client_host
+             order by max(query_time_ms) desc",
+            CLIENT_HOST, SAMPLED_SESSIONS
+        ))?;
+        let rows = statement.query_map(params![millis(from), millis(to), min_time.as_millis() as i64], |row| {
+            Ok(ClientHistory {
+                api: row.get(0)?,
+                client_host: row.get(1)?,
+                sessions: row.get::<_, i64>(2)? as u64,
+                samples: row.get::<_, i64>(3)? as u64,
+                sampled_time_ms: row.get::<_, i64>(4)? as u64,
+                max_time_ms: row.get::<_, i64>(5)? as u64,
+                last_seen: time(row.get(6)?),
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+}
+
+// sqlite integers are signed.
+fn millis(timestamp: SystemTime) -> i64 {
+    timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64
+}
+
+fn time(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis as u64)
+}
+
+fn format_time(timestamp: SystemTime) -> String {
+    DateTime::<Local>::from(timestamp).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Parse a local time: 2022-03-27 02:00, with optional seconds, a date for its midnight,
+/// 02:00 for today, or today or yesterday for their midnight.
+pub fn parse_time(text: &str) -> Result<SystemTime, String> {
+    let today = Local::now().date_naive();
+    let text = text.trim();
+    let time = match text {
+        "today" => Some(today.and_time(NaiveTime::MIN)),
+        "yesterday" => today.pred_opt().map(|d| d.and_time(NaiveTime::MIN)),
+        _ => NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M"))
+            .ok()
+            .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().map(|d| d.and_time(NaiveTime::MIN)))
+            .or_else(|| {
+                NaiveTime::parse_from_str(text, "%H:%M:%S")
+                    .or_else(|_| NaiveTime::parse_from_str(text, "%H:%M"))
+                    .ok()
+                    .map(|t| today.and_time(t))
+            }),
+    };
+    time.and_then(|time| Local.from_local_datetime(&time).earliest())
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("invalid time: {}", text))
+}
+
+pub fn write_statements(out: &mut dyn Write, statements: &[StatementHistory]) -> io::Result<()> {
+    writeln!(
+        out,
+        "{:4} {:>9} {:>7} {:>8} {:19} {:19} fingerprint",
+        "API", "sampled_s", "samples", "max_s", "first_seen", "last_seen"
+    )?;
+    for statement in statements {
+        writeln!(
+            out,
+            "{:4} {:9.1} {:7} {:8.3} {:19} {:19} {}",
+            statement.api,
+            statement.sampled_time_ms as f64 / 1000.0,
+            statement.samples,
+            statement.max_time_ms as f64 / 1000.0,
+            format_time(statement.first_seen),
+            format_time(statement.last_seen),
+            statement.fingerprint
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_clients(out: &mut dyn Write, clients: &[ClientHistory]) -> io::Result<()> {
+    writeln!(
+        out,
+        "{:4} {:20} {:>8} {:>9} {:>7} {:>8} last_seen",
+        "API", "client", "sessions", "sampled_s", "samples", "max_s"
+    )?;
+    for client in clients {
+        writeln!(
+            out,
+            "{:4} {:20} {:8} {:9.1} {:7} {:8.3} {}",
+            client.api,
+            client.client_host,
+            client.sessions,
+            client.sampled_time_ms as f64 / 1000.0,
+            client.samples,
+            client.max_time_ms as f64 / 1000.0,
+            format_time(client.last_seen)
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::fixtures::snapshot;
+
+    fn ysql_session(port: u32, query: &str, ms: u32) -> String {
+        ysql_session_of("10.0.0.5", port, query, ms)
+    }
+
+    fn ysql_session_of(host: &str, port: u32, query: &str, ms: u32) -> String {
+        format!(
+            r#"{{"connections": [{{"process_start_time": "2022-03-27 15:55:22.587029+00", "application_name": "ysqlsh", "backend_type": "client backend", "backend_status": "active", "db_name": "yugabyte", "host": "{}", "port": "{}", "query": "{}", "query_running_for_ms": {}}}]}}"#,
+            host, port, query, ms
+        )
+    }
+
+    #[test]
+    fn write_and_query_history() {
+        let mut history = History::with_connection(Connection::open_in_memory().unwrap())
+            .unwrap()
+            .with_refresh_interval(Duration::from_secs(3));
+        history
+            .write(&snapshot(1000, &[("192.168.66.80:13000", &ysql_session(50736, "select * from t where id = 1", 40000))]))
+            .unwrap();
+        history
+            .write(&snapshot(1003, &[("192.168.66.80:13000", &ysql_session(50737, "select * from t where id = 2", 2000))]))
+            .unwrap();
+        history.write(&snapshot(1006, &[("192.168.66.80:13000", "not json")])).unwrap();
+        history
+            .write(&snapshot(2000, &[("192.168.66.80:13000", &ysql_session(50738, "select 1", 50000))]))
+            .unwrap();
+
+        let samples: u32 = history.connection.query_row("select count(*) from samples", [], |row| row.get(0)).unwrap();
+        assert_eq!(samples, 4);
+
+        let statements = history.top_statements(time(1_000_000), time(1_010_000), 10).unwrap();
+        assert_eq!(statements.len(), 1);
+        assert_eq!(statements[0].fingerprint, "select * from t where id = ?");
+        assert_eq!(statements[0].samples, 2);
+        // the first sample of a run has no interval.
+        assert_eq!(statements[0].sampled_time_ms, 3000);
+        assert_eq!(statements[0].max_time_ms, 40000);
+        assert_eq!(statements[0].last_seen, time(1_003_000));
+
+        let clients = history.long_running_clients(time(0), time(3_000_000), Duration::from_secs(30)).unwrap();
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[0].client_host, "10.0.0.5");
+        assert_eq!(clients[0].sessions, 2);
+        assert_eq!(clients[0].max_time_ms, 50000);
+        // after a gap, the sample counts for one refresh interval.
+        assert_eq!(clients[0].sampled_time_ms, 3000);
+    }
+
+    #[test]
+    fn ipv6_client_hosts() {
+        let mut history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        for (seconds, host, port) in [(1000, "fe80::1", 50736), (1002, "fe80::1", 50737), (1004, "::1", 50738)] {
+            history
+                .write(&snapshot(seconds, &[("192.168.66.80:13000", &ysql_session_of(host, port, "select pg_sleep(60)", 40000))]))
+                .unwrap();
+        }
+        let clients = history.long_running_clients(time(0), time(3_000_000), Duration::from_secs(30)).unwrap();
+        let mut hosts: Vec<(&str, u64, u64)> =
+            clients.iter().map(|c| (c.client_host.as_str(), c.sessions, c.sampled_time_ms)).collect();
+        hosts.sort();
+        assert_eq!(hosts, vec![("::1", 1, 2000), ("fe80::1", 2, 2000)]);
+    }
+
+    #[test]
+    fn parse_times() {
+        let today = parse_time("today").unwrap();
+        assert!(parse_time("yesterday").unwrap() < today);
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(parse_time(&date).unwrap(), today);
+        assert_eq!(parse_time("12:15").unwrap(), parse_time(&format!("{} 12:15", date)).unwrap());
+        assert_eq!(
+            parse_time("2022-03-27 02:15").unwrap(),
+            parse_time("2022-03-27 02:15:00").unwrap()
+        );
+        assert!(parse_time("last week").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! The connection inventory: who is connected where. All connections are counted, also the idle ones
+//! and the YSQL backends that don't serve a client.
+use crate::rpcz::AllConnections::{Connections, InboundConnections};
+use crate::snapshot::ClusterSnapshot;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// The number of connections with the same server, client host, application, db or keyspace and state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionCount {
+    /// the endpoint, so the port tells the API.
+    pub server: String,
+    pub client_host: String,
+    /// the YSQL application_name, or for a backend without one, its backend_type.
+    pub application: String,
+    pub db_keyspace: String,
+    pub state: String,
+    pub count: usize,
+}
+
+/// The host of an address, without its port.
+fn host(address: &str) -> &str {
+    address.rsplit_once(':').map_or(address, |(host, _)| host)
+}
+
+/// Count the connections of all endpoints, the largest counts first.
+pub fn connection_counts(snapshot: &ClusterSnapshot) -> Vec<ConnectionCount> {
+    let mut counts: BTreeMap<(String, String, String, String, String), usize> = BTreeMap::new();
+    for endpoint_snapshot in &snapshot.endpoints {
+        let server = endpoint_snapshot.endpoint.to_string();
+        match &endpoint_snapshot.connections {
+            Some(Connections { connections }) => {
+                for connection in connections {
+                    let application = match &connection.application_name {
+                        name if name.is_empty() => &connection.backend_type,
+                        name => name,
+                    };
+                    let key = (
+                        server.clone(),
+                        connection.host.clone().unwrap_or_default(),
+                        application.clone(),
+                        connection.db_name.clone().unwrap_or_default(),
+                        connection.backend_status.clone().unwrap_or_default(),
+                    );
+                    *counts.entry(key).or_default() += 1;
+                }
+            }
+            Some(InboundConnections { inbound_connections }) => {
+                for connection in inbound_connections {
+                    let keyspace = connection
+                        .connection_details
+                        .as_ref()
+                        .map(|details| details.cql_connection_details.keyspace.clone())
+                        .unwrap_or_default();
+                    let key = (
+                        server.clone(),
+                        host(&connection.remote_ip).to_string(),
+                        String::new(),
+                        keyspace,
+                        connection.state.clone(),
+                    );
+                    *counts.entry(key).or_default() += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut counts: Vec<ConnectionCount> = counts
+        .into_iter()
+        .map(|((server, client_host, application, db_keyspace, state), count)| ConnectionCount {
+            server,
+            client_host,
+            application,
+            db_keyspace,
+            state,
+            count,
+        })
+        .collect();
+    // the sort is stable, so equal counts stay in the order of server and client.
+    counts.sort_by_key(|count| std::cmp::Reverse(count.count));
+    counts
+}
+
+/// The number of connections per node, of all its endpoints, in the order of the nodes.
+pub fn node_totals(counts: &[ConnectionCount]) -> Vec<(String, usize)> {
+    let mut totals: BTreeMap<String, usize> = BTreeMap::new();
+    for count in counts {
+        *totals.entry(host(&count.server).to_string()).or_default() += count.count;
+    }
+    totals.into_iter().collect()
+}
+
+pub fn write_inventory(out: &mut dyn Write, counts: &[ConnectionCount]) -> io::Result<()> {
+    writeln!(out, "{:>6} {:26} {:20} {:20} {:10} state", "count", "server", "client", "application", "db/keysp")?;
+    for count in counts {
+        writeln!(
+            out,
+            "{:6} {:26} {:20} {:20} {:10} {}",
+            count.count, count.server, count.client_host, count.application, count.db_keyspace, count.state
+        )?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:>6} {:26}", "total", "node")?;
+    for (node, total) in node_totals(counts) {
+        writeln!(out, "{:6} {:26}", total, node)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::fixtures;
+
+    #[test]
+    fn count_connections() {
+        let ysql = r#"{"connections": [
+            {"process_start_time": "2022-03-27 15:54:29.555678+00", "application_name": "", "backend_type": "checkpointer", "backend_status": ""},
+            {"process_start_time": "2022-03-27 15:55:22.587029+00", "application_name": "app", "backend_type": "client backend", "backend_status": "idle", "db_name": "yugabyte", "host": "10.0.0.5", "port": "50736"},
+            {"process_start_time": "2022-03-27 15:55:23.587029+00", "application_name": "app", "backend_type": "client backend", "backend_status": "idle", "db_name": "yugabyte", "host": "10.0.0.5", "port": "50737"}
+        ]}"#;
+        let ycql = r#"{"inbound_connections": [
+            {"remote_ip": "10.0.0.6:40000", "state": "OPEN", "processed_call_count": 5, "connection_details": {"cql_connection_details": {"keyspace": "cr"}}}
+        ]}"#;
+        let snapshot = fixtures::snapshot(
+            0,
+            &[("192.168.66.80:13000", ysql), ("192.168.66.80:12000", ycql), ("192.168.66.81:12000", ycql)],
+        );
+        let counts = connection_counts(&snapshot);
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts[0].client_host, "10.0.0.5");
+        assert_eq!(counts[0].count, 2);
+        assert_eq!(counts[1].client_host, "10.0.0.6");
+        assert_eq!(counts[1].db_keyspace, "cr");
+        assert_eq!(counts[2].application, "checkpointer");
+        assert_eq!(counts[3].server, "192.168.66.81:12000");
+        // the totals add up the endpoints of a node.
+        assert_eq!(node_totals(&counts), vec![(String::from("192.168.66.80"), 4), (String::from("192.168.66.81"), 1)]);
+    }
+}
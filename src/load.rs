@@ -0,0 +1,128 @@
+//! The load average of the cluster: the average number of active sessions (AAS) over the last
+//! 1, 5 and 15 minutes, from the number of session rows per API at every refresh.
+use crate::snapshot::ClusterSnapshot;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+pub const APIS: [&str; 2] = ["YSQL", "YCQL"];
+const WINDOWS: [Duration; 3] = [Duration::from_secs(60), Duration::from_secs(300), Duration::from_secs(900)];
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Default)]
+pub struct LoadTracker {
+    /// the time of every refresh of the last 15 minutes, with its active sessions per API in the order of APIS.
+    samples: VecDeque<(SystemTime, [usize; 2])>,
+}
+
+impl LoadTracker {
+    pub fn new() -> Self {
+        LoadTracker::default()
+    }
+
+    pub fn update(&mut self, snapshot: &ClusterSnapshot) {
+        let mut counts = [0; 2];
+        for row in snapshot.sessions(false) {
+            if let Some(api) = APIS.iter().position(|api| *api == row.api) {
+                counts[api] += 1;
+            }
+        }
+        self.samples.push_back((snapshot.timestamp, counts));
+        let longest = WINDOWS[WINDOWS.len() - 1];
+        while self
+            .samples
+            .front()
+            .is_some_and(|(timestamp, _)| snapshot.timestamp.duration_since(*timestamp).unwrap_or_default() > longest)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The average active sessions of an API over the refreshes of the last 1, 5 and 15 minutes.
+    pub fn averages(&self, api: &str) -> [f64; 3] {
+        let api = match APIS.iter().position(|a| *a == api) {
+            Some(api) => api,
+            None => return [0.0; 3],
+        };
+        let last = match self.samples.back() {
+            Some((timestamp, _)) => *timestamp,
+            None => return [0.0; 3],
+        };
+        WINDOWS.map(|window| {
+            let counts: Vec<usize> = self
+                .samples
+                .iter()
+                .filter(|(timestamp, _)| last.duration_since(*timestamp).unwrap_or_default() <= window)
+                .map(|(_, counts)| counts[api])
+                .collect();
+            counts.iter().sum::<usize>() as f64 / counts.len() as f64
+        })
+    }
+
+    /// A sparkline of the active sessions of all APIs of the last refreshes, scaled to their maximum.
+    pub fn sparkline(&self, refreshes: usize) -> String {
+        let totals: Vec<usize> = self
+            .samples
+            .iter()
+            .skip(self.samples.len().saturating_sub(refreshes))
+            .map(|(_, counts)| counts.iter().sum())
+            .collect();
+        let max = totals.iter().copied().max().unwrap_or_default().max(1);
+        totals
+            .iter()
+            .map(|total| SPARKS[total * (SPARKS.len() - 1) / max])
+            .collect()
+    }
+}
+
+/// Write the load averages per API and the sparkline of the last refreshes on one line.
+pub fn write_load(out: &mut dyn Write, load: &LoadTracker, refreshes: usize) -> io::Result<()> {
+    let averages: Vec<String> = APIS
+        .iter()
+        .map(|api| {
+            let [one, five, fifteen] = load.averages(api);
+            format!("{} {:.2} {:.2} {:.2}", api, one, five, fifteen)
+        })
+        .collect();
+    writeln!(out, "active sessions 1/5/15m: {}  {}", averages.join("  "), load.sparkline(refreshes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::fixtures;
+
+    fn snapshot(seconds: u64, active: usize) -> ClusterSnapshot {
+        let connections: Vec<String> = (0..active)
+            .map(|i| {
+                format!(
+                    r#"{{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_status": "active", "host": "10.0.0.5", "port": "{}"}}"#,
+                    50000 + i
+                )
+            })
+            .collect();
+        fixtures::snapshot(seconds, &[("192.168.66.80:13000", &fixtures::ysql(&connections))])
+    }
+
+    #[test]
+    fn average_active_sessions() {
+        let mut load = LoadTracker::new();
+        // 2 active sessions every 30 seconds for 10 minutes, then a spike of 8 for a minute.
+        for n in 0..20 {
+            load.update(&snapshot(n * 30, 2));
+        }
+        load.update(&snapshot(600, 8));
+        load.update(&snapshot(630, 8));
+        load.update(&snapshot(660, 8));
+        let [one, five, fifteen] = load.averages("YSQL");
+        assert_eq!(one, 8.0);
+        assert_eq!(five, (8.0 * 3.0 + 2.0 * 8.0) / 11.0);
+        assert_eq!(fifteen, (8.0 * 3.0 + 2.0 * 20.0) / 23.0);
+        assert_eq!(load.averages("YCQL"), [0.0; 3]);
+        assert_eq!(load.sparkline(5), "▂▂███");
+
+        // samples older than 15 minutes are gone.
+        load.update(&snapshot(2000, 0));
+        assert_eq!(load.averages("YSQL"), [0.0; 3]);
+    }
+}
@@ -0,0 +1,372 @@
+//! Blocking chains of YSQL sessions: with a SQL connection per node, the backends that wait on a lock
+//! and the backends that hold it are read, and are shown as a tree of blocker and waiters. The root
+//! blocker, which waits on nothing itself, is the session to act on.
+//!
+//! YugabyteDB doesn't keep its locks in the lock table of PostgreSQL, so pg_blocking_pids doesn't see
+//! them. From 2.20, pg_locks reads them with yb_lock_status, and its ybdetails column has the
+//! transaction of a lock and, for a lock that is waited on, the transactions it waits on in blocked_by.
+//! These are only filled with the wait queues of wait-on-conflict concurrency control
+//! (enable_wait_queues), and only for the transactions older than yb_locks_min_txn_age, a second by
+//! default. Without ybdetails, on older versions and on PostgreSQL, pg_blocking_pids is read.
+use crate::error::Error;
+use crate::rpcz::AllConnections::Connections;
+use crate::severity;
+use crate::snapshot::{ClusterSnapshot, GeneralPresentation};
+use crate::syntax;
+use crate::tracking::TrackedRow;
+use crate::ysql::{ClientPool, Login};
+use postgres::Client;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{self, Write};
+
+/// Whether pg_locks has the ybdetails of YugabyteDB.
+const HAS_YB_DETAILS: &str = "select exists (
+    select from pg_attribute where attrelid = 'pg_catalog.pg_locks'::regclass and attname = 'ybdetails'
+)";
+
+/// The backends that wait on a lock, with the backends they wait on, by pg_blocking_pids.
+const WAITING: &str = "select pid, pg_blocking_pids(pid) as blocked_by from pg_stat_activity where cardinality(pg_blocking_pids(pid)) > 0";
+
+/// The backends that wait on a lock, with the backends of the node that hold the transactions they
+/// wait on, by the transaction ids of the ybdetails of pg_locks. pg_locks has the locks of the whole
+/// cluster, of which those with the pid of a backend of the node are kept.
+const YB_WAITING: &str = "with locks as (
+    select pid, granted, ybdetails->>'transactionid' as transaction,
+        case jsonb_typeof(ybdetails->'blocked_by') when 'array' then ybdetails->'blocked_by' else '[]' end as blocked_by
+    from pg_locks where pid in (select pid from pg_stat_activity)
+)
+select w.pid, array_agg(distinct h.pid) as blocked_by
+from locks w cross join jsonb_array_elements_text(w.blocked_by) as b(transaction)
+join locks h on h.transaction = b.transaction and h.pid <> w.pid
+where not w.granted
+group by w.pid";
+
+/// The backends that wait on a lock or hold one that is waited on, with the backends they wait on,
+/// after the waiting backends.
+const BLOCKED_BACKENDS: &str = "
+select a.pid, coalesce(host(a.client_addr), ''), coalesce(a.client_port, -1), coalesce(a.state, ''), coalesce(a.query, ''),
+    coalesce(extract(epoch from now() - a.xact_start), 0)::float8, coalesce(w.blocked_by, '{}')
+from pg_stat_activity a left join waiting w on w.pid = a.pid
+where a.pid in (select pid from waiting union select unnest(blocked_by) from waiting)
+order by a.pid";
+
+/// The style of a root blocker.
+pub const ROOT_STYLE: severity::Style = ("\x1b[1;31m", "\x1b[22;39m");
+
+/// A backend in a blocking chain on a server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockedBackend {
+    pub server: String,
+    pub pid: i32,
+    /// the client address and port, as in the session list, or empty without a client.
+    pub client: String,
+    pub state: String,
+    pub query: String,
+    pub transaction_seconds: f64,
+    /// the pids of the backends of the same server this one waits on.
+    pub blocked_by: Vec<i32>,
+}
+
+impl BlockedBackend {
+    /// A root blocker waits on nothing itself.
+    pub fn is_root(&self) -> bool {
+        self.blocked_by.is_empty()
+    }
+}
+
+/// Whether the locks of a server are read from the ybdetails of pg_locks.
+pub fn has_yb_details(client: &mut Client) -> Result<bool, Error> {
+    Ok(client.query_one(HAS_YB_DETAILS, &[])?.get(0))
+}
+
+/// The backends in blocking chains on a server, by the ybdetails of pg_locks or by pg_blocking_pids.
+pub fn blocked_backends(client: &mut Client, server: &str, yb_details: bool) -> Result<Vec<BlockedBackend>, Error> {
+    let query = format!("with waiting as ({}){}", if yb_details { YB_WAITING } else { WAITING }, BLOCKED_BACKENDS);
+    Ok(client
+        .query(query.as_str(), &[])?
+        .iter()
+        .map(|row| {
+            let address: String = row.get(1);
+            let port: i32 = row.get(2);
+            BlockedBackend {
+                server: server.to_string(),
+                pid: row.get(0),
+                client: if address.is_empty() { String::new() } else { format!("{}:{}", address, port) },
+                state: row.get(3),
+                query: row.get(4),
+                transaction_seconds: row.get(5),
+                blocked_by: row.get(6),
+            }
+        })
+        .collect())
+}
+
+/// Keeps a connection to every node, to read the blocking chains at every refresh.
+pub struct LockCollector {
+    clients: ClientPool,
+    /// whether the locks of a server are read from ybdetails, asked again after an error.
+    yb_details: BTreeMap<String, bool>,
+}
+
+impl LockCollector {
+    pub fn new(login: Login, database: &str) -> Self {
+        LockCollector {
+            clients: ClientPool::new(login, database),
+            yb_details: BTreeMap::new(),
+        }
+    }
+
+    /// The backends in blocking chains on the servers, and the errors of the servers that couldn't tell.
+    /// A connection that fails is made again at the next refresh.
+    pub fn collect(&mut self, servers: &[String]) -> (Vec<BlockedBackend>, Vec<String>) {
+        let mut backends = Vec::new();
+        let mut errors = Vec::new();
+        for server in servers {
+            let known = self.yb_details.get(server).copied();
+            let result = self.clients.read(server, |client| {
+                let yb_details = match known {
+                    Some(yb_details) => yb_details,
+                    None => has_yb_details(client)?,
+                };
+                Ok((yb_details, blocked_backends(client, server, yb_details)?))
+            });
+            match result {
+                Ok((yb_details, blocked)) => {
+                    self.yb_details.insert(server.clone(), yb_details);
+                    backends.extend(blocked);
+                }
+                Err(e) => {
+                    self.yb_details.remove(server);
+                    errors.push(format!("locks of {}: {}", server, e));
+                }
+            }
+        }
+        (backends, errors)
+    }
+}
+
+/// The servers with YSQL sessions.
+pub fn ysql_servers(snapshot: &ClusterSnapshot) -> Vec<String> {
+    let servers: BTreeSet<String> = snapshot
+        .endpoints
+        .iter()
+        .filter(|e| matches!(e.connections, Some(Connections { .. })))
+        .map(|e| e.endpoint.hostname.clone())
+        .collect();
+    servers.into_iter().collect()
+}
+
+/// The backend of a row of the session list, when it is in a blocking chain.
+pub fn backend_of<'a>(backends: &'a [BlockedBackend], row: &GeneralPresentation) -> Option<&'a BlockedBackend> {
+    if row.api != "YSQL" {
+        return None;
+    }
+    backends
+        .iter()
+        .find(|backend| backend.server == row.server && !backend.client.is_empty() && backend.client == row.client)
+}
+
+/// The root blockers, with the number of backends that wait on them, directly or through another.
+/// A backend that waits on more than one in the tree of a root blocker is counted once.
+pub fn root_blockers(backends: &[BlockedBackend]) -> Vec<(&BlockedBackend, usize)> {
+    let mut roots: Vec<(&BlockedBackend, HashSet<i32>)> = Vec::new();
+    // whether the tree that is counted is that of a root blocker, and not a deadlock.
+    let mut in_root = false;
+    for (depth, backend) in chains(backends) {
+        if depth == 0 {
+            in_root = backend.is_root();
+            if in_root {
+                roots.push((backend, HashSet::new()));
+            }
+        } else if let (true, Some((_, waiters))) = (in_root, roots.last_mut()) {
+            waiters.insert(backend.pid);
+        }
+    }
+    roots.into_iter().map(|(root, waiters)| (root, waiters.len())).collect()
+}
+
+/// The backends in blocking chains in the order of a tree, each with its depth: every root blocker,
+/// followed by the backends that wait on it, indented, and so on. A backend that waits on more than
+/// one is shown under each, and backends that only wait on each other, in a deadlock, are shown from the first of them.
+pub fn chains(backends: &[BlockedBackend]) -> Vec<(usize, &BlockedBackend)> {
+    fn add<'a>(
+        backend: &'a BlockedBackend,
+        depth: usize,
+        backends: &'a [BlockedBackend],
+        path: &mut Vec<i32>,
+        shown: &mut HashSet<(&'a str, i32)>,
+        tree: &mut Vec<(usize, &'a BlockedBackend)>,
+    ) {
+        tree.push((depth, backend));
+        shown.insert((&backend.server, backend.pid));
+        path.push(backend.pid);
+        let waiters: Vec<&BlockedBackend> = backends
+            .iter()
+            .filter(|w| w.server == backend.server && w.blocked_by.contains(&backend.pid) && !path.contains(&w.pid))
+            .collect();
+        for waiter in waiters {
+            add(waiter, depth + 1, backends, path, shown, tree);
+        }
+        path.pop();
+    }
+    let mut tree = Vec::new();
+    let mut shown = HashSet::new();
+    for root in backends.iter().filter(|b| b.is_root()) {
+        add(root, 0, backends, &mut Vec::new(), &mut shown, &mut tree);
+    }
+    for backend in backends {
+        if !shown.contains(&(backend.server.as_str(), backend.pid)) {
+            add(backend, 0, backends, &mut Vec::new(), &mut shown, &mut tree);
+        }
+    }
+    tree
+}
+
+/// Write the blocking chains, with the time and query of the rows of the session list where the backend
+/// is one of them, and the root blockers highlighted.
+pub fn write_chains(out: &mut dyn Write, backends: &[BlockedBackend], rows: &[TrackedRow], color: bool) -> io::Result<()> {
+    if backends.is_empty() {
+        return writeln!(out, "No sessions wait on a lock");
+    }
+    writeln!(
+        out,
+        "{:16} {:20} {:20} {:20} {:>8} {:>8} query",
+        "pid", "server", "client", "state", "xact_s", "time_s"
+    )?;
+    for (depth, backend) in chains(backends) {
+        let row = rows
+            .iter()
+            .map(|tracked| &tracked.row)
+            .find(|row| backend_of(std::slice::from_ref(backend), row).is_some());
+        let pid = if depth == 0 {
+            format!("{} {}", backend.pid, if backend.is_root() { "root" } else { "cycle" })
+        } else {
+            format!("{}└ {}", "  ".repeat(depth - 1), backend.pid)
+        };
+        let line = format!(
+            "{:16} {:20} {:20} {:20} {:8.1} {:>8} {}",
+            pid,
+            backend.server,
+            backend.client,
+            backend.state,
+            backend.transaction_seconds,
+            row.map(|row| format!("{:.3}", row.query_time_ms as f32 / 1000.0)).unwrap_or_default(),
+            syntax::one_line(row.map_or(&backend.query, |row| &row.query))
+        );
+        let style = if color && backend.is_root() { Some(ROOT_STYLE) } else { None };
+        writeln!(out, "{}", severity::styled(&line, style))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::fixtures;
+    use crate::ysql;
+
+    fn backend(pid: i32, state: &str, blocked_by: &[i32]) -> BlockedBackend {
+        BlockedBackend {
+            server: String::from("192.168.66.80"),
+            pid,
+            client: format!("10.0.0.5:{}", 50000 + pid),
+            state: state.to_string(),
+            query: format!("update t set a = {} where id = 1", pid),
+            transaction_seconds: 12.0,
+            blocked_by: blocked_by.to_vec(),
+        }
+    }
+
+    #[test]
+    fn blocking_tree() {
+        // 10 blocks 11, which blocks 12, and 10 also blocks 13; 14 waits on 11 and 13; 20 and 21 wait on each other.
+        let backends = vec![
+            backend(11, "active", &[10]),
+            backend(10, "idle in transaction", &[]),
+            backend(12, "active", &[11]),
+            backend(13, "active", &[10]),
+            backend(14, "active", &[11, 13]),
+            backend(21, "active", &[20]),
+            backend(20, "active", &[21]),
+        ];
+        let tree: Vec<(usize, i32)> = chains(&backends).into_iter().map(|(depth, b)| (depth, b.pid)).collect();
+        assert_eq!(tree, vec![(0, 10), (1, 11), (2, 12), (2, 14), (1, 13), (2, 14), (0, 21), (1, 20)]);
+        // 14 is shown twice, and counted once.
+        let roots: Vec<(i32, usize)> = root_blockers(&backends).into_iter().map(|(b, waiters)| (b.pid, waiters)).collect();
+        assert_eq!(roots, vec![(10, 4)]);
+
+        let mut out = Vec::new();
+        write_chains(&mut out, &backends[..3], &[], false).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[1].starts_with("10 root          192.168.66.80        10.0.0.5:50010       idle in transaction      12.0          update t set a = 10"));
+        assert!(lines[3].starts_with("  └ 12 "));
+    }
+
+    #[test]
+    fn servers_with_ysql_sessions() {
+        let ysql = fixtures::ysql(&[]);
+        let ycql = fixtures::ycql(&[]);
+        let snapshot = fixtures::snapshot(
+            0,
+            &[("192.168.66.81:13000", &ysql), ("192.168.66.80:13000", &ysql), ("192.168.66.81:13000", &ysql), ("192.168.66.82:12000", &ycql)],
+        );
+        assert_eq!(ysql_servers(&snapshot), vec!["192.168.66.80", "192.168.66.81"]);
+    }
+
+    #[test]
+    #[ignore = "needs a local PostgreSQL, see ysql::test_login"]
+    fn local_lock_wait() {
+        let login = ysql::test_login();
+        let mut holder = login.connect("127.0.0.1", "postgres").unwrap();
+        holder.batch_execute("create table if not exists ybtop_locks (id int)").unwrap();
+        holder.batch_execute("begin; lock table ybtop_locks").unwrap();
+        let mut waiter = login.connect("127.0.0.1", "postgres").unwrap();
+        let waiting = std::thread::spawn(move || waiter.simple_query("select * from ybtop_locks").map(|_| ()));
+        let mut collector = LockCollector::new(login, "postgres");
+        let servers = vec![String::from("127.0.0.1")];
+        let backends = (0..50)
+            .map(|_| {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                collector.collect(&servers).0
+            })
+            .find(|backends| backends.len() == 2)
+            .unwrap();
+        let tree = chains(&backends);
+        assert_eq!(tree[0].1.state, "idle in transaction");
+        assert!(tree[0].1.is_root());
+        assert_eq!(tree[1].0, 1);
+        assert_eq!(tree[1].1.query, "select * from ybtop_locks");
+        holder.batch_execute("rollback; drop table ybtop_locks;").unwrap();
+        waiting.join().unwrap().unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a local PostgreSQL, see ysql::test_login"]
+    fn local_yb_lock_wait() {
+        let login = ysql::test_login();
+        // PostgreSQL has no ybdetails, so pg_locks is replaced by a view as YugabyteDB shows a wait,
+        // of the holder and the waiter, which waits on the transaction of the holder and one of another node.
+        let mut holder = login.connect("127.0.0.1", "postgres").unwrap();
+        let mut waiter = login.connect("127.0.0.1", "postgres").unwrap();
+        let holder_pid: i32 = holder.query_one("select pg_backend_pid()", &[]).unwrap().get(0);
+        let waiter_pid: i32 = waiter.query_one("select pg_backend_pid()", &[]).unwrap().get(0);
+        assert!(!has_yb_details(&mut holder).unwrap());
+        holder
+            .batch_execute(&format!(
+                "set search_path = pg_temp, pg_catalog;
+                create temporary view pg_locks as select * from (values
+                    ({}, true, '{{\"transactionid\": \"t1\"}}'::jsonb),
+                    ({}, false, '{{\"transactionid\": \"t2\", \"blocked_by\": [\"t1\", \"t3\"]}}'::jsonb),
+                    (null, true, '{{\"transactionid\": \"t3\"}}'::jsonb)
+                ) as l(pid, granted, ybdetails)",
+                holder_pid, waiter_pid
+            ))
+            .unwrap();
+        let backends = blocked_backends(&mut holder, "127.0.0.1", true).unwrap();
+        let edges: Vec<(i32, Vec<i32>)> = backends.into_iter().map(|b| (b.pid, b.blocked_by)).collect();
+        let mut expected = vec![(holder_pid, vec![]), (waiter_pid, vec![holder_pid])];
+        expected.sort();
+        assert_eq!(edges, expected);
+    }
+}
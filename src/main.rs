@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -5,7 +7,7 @@ struct Opts {
     /// hostnames, comma separated.
     #[structopt(short, long, default_value = "192.168.66.80,192.168.66.81,192.168.66.82")]
     hosts: String,
-    /// ports numbers, comma separated. YSQL:13000, YCQL:12000
+    /// ports numbers, comma separated. YSQL:13000, YCQL:12000, YEDIS:11000
     #[structopt(short, long, default_value = "13000,12000")]
     ports: String,
     /// update interval
@@ -14,16 +16,171 @@ struct Opts {
     /// show idle sessions
     #[structopt(short, long)]
     idle: bool,
+    /// master web addresses (host:port), comma separated, to rank tables and tablets by tserver calls in flight
+    #[structopt(short, long, default_value = "")]
+    masters: String,
+    /// show the YSQL connection manager pools
+    #[structopt(short, long)]
+    connection_manager: bool,
+    /// write every page that is read to this directory, to replay it later
+    #[structopt(long, parse(from_os_str))]
+    record: Option<PathBuf>,
+    /// replay a recording instead of reading the endpoints
+    #[structopt(long, parse(from_os_str))]
+    replay: Option<PathBuf>,
+    /// add the sessions of every refresh to this SQLite database
+    #[structopt(long, parse(from_os_str))]
+    history: Option<PathBuf>,
+    /// show the queries that finish after running this many seconds or longer
+    #[structopt(long, default_value = "10")]
+    slow_threshold: u64,
+    /// append the queries that finish after running the slow threshold or longer to this file
+    #[structopt(long, parse(from_os_str))]
+    slow_log: Option<PathBuf>,
+    /// the busiest server's active sessions divided by the mean per server, from which an API is skewed
+    #[structopt(long, default_value = "2")]
+    skew_threshold: f64,
+    /// warn when the same server is skewed for more than this many refreshes
+    #[structopt(long, default_value = "5")]
+    skew_refreshes: usize,
+    /// show only the sessions that match all filters, such as api=YCQL,db=orders,client~10.1.,time>5s,status!=QUERY
+    #[structopt(short, long)]
+    filter: Vec<String>,
+    /// don't color the queries; they aren't when the output is not a terminal or NO_COLOR is set
+    #[structopt(long)]
+    no_color: bool,
+    /// seconds from which the time of a query is shown yellow, red and blinking, comma separated
+    #[structopt(long, default_value = "1,10,60", parse(try_from_str = ybtop::severity::parse_thresholds))]
+    time_colors: ybtop::severity::Thresholds,
+    /// the YSQL port to connect to, to cancel or terminate a session or explain its statement
+    #[structopt(long, default_value = "5433")]
+    ysql_port: u16,
+    /// the YSQL user to connect as, with the password in PGPASSWORD
+    #[structopt(long, default_value = "yugabyte")]
+    ysql_user: String,
+    /// the YSQL database to connect to, to read the locks and pg_stat_activity
+    #[structopt(long, default_value = "yugabyte")]
+    ysql_database: String,
+    /// connect to the YSQL port of every node to show the sessions that wait on a lock under the sessions they wait on
+    #[structopt(long)]
+    locks: bool,
+    /// connect to the YSQL port of every node to add the pid, user, wait event and query id of pg_stat_activity to the YSQL sessions
+    #[structopt(long)]
+    pg_stat_activity: bool,
+    #[structopt(subcommand)]
+    command: Option<Subcommand>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Subcommand {
+    /// show the sessions and query fingerprints in saved /rpcz files, named by host and time
+    Analyze {
+        /// show idle sessions
+        #[structopt(short, long)]
+        idle: bool,
+        /// the saved /rpcz files, or directories with them
+        #[structopt(parse(from_os_str), required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// answer questions from a history database written with --history
+    Query {
+        /// the history database
+        #[structopt(parse(from_os_str))]
+        history: PathBuf,
+        /// from this local time: 2022-03-27 02:00, 02:00 for today, today or yesterday
+        #[structopt(long, default_value = "yesterday", parse(try_from_str = ybtop::history::parse_time))]
+        from: SystemTime,
+        /// until this local time
+        #[structopt(long, parse(try_from_str = ybtop::history::parse_time))]
+        to: Option<SystemTime>,
+        #[structopt(subcommand)]
+        question: Question,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum Question {
+    /// the statements with the most sampled time
+    Statements {
+        /// the number of statements
+        #[structopt(short, long, default_value = "20")]
+        limit: u32,
+    },
+    /// the client hosts with sessions running for longer than the minimum
+    Clients {
+        /// the minimum running time in seconds
+        #[structopt(long, default_value = "30")]
+        min_seconds: u64,
+    },
 }
 
 fn main() {
 
     let options = Opts::from_args();
+    if let Some(Subcommand::Analyze { idle, files }) = &options.command {
+        let result = ybtop::analyze::read_files(files)
+            .and_then(|snapshots| ybtop::analyze::write_analysis(&mut std::io::stdout().lock(), &snapshots, *idle));
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(Subcommand::Query { history, from, to, question }) = &options.command {
+        let to = to.unwrap_or_else(SystemTime::now);
+        let mut out = std::io::stdout().lock();
+        let result = ybtop::history::History::open(history).and_then(|history| match question {
+            Question::Statements { limit } => {
+                let statements = history.top_statements(*from, to, *limit)?;
+                Ok(ybtop::history::write_statements(&mut out, &statements)?)
+            }
+            Question::Clients { min_seconds } => {
+                let clients = history.long_running_clients(*from, to, Duration::from_secs(*min_seconds))?;
+                Ok(ybtop::history::write_clients(&mut out, &clients)?)
+            }
+        });
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     let hostname_vec: Vec<&str> = options.hosts.split(",").collect();
     let port_vec: Vec<&str> = options.ports.split(",").collect();
-    let update_interval: u64 = options.update;
-    let idle: bool = options.idle;
+    let settings = ybtop::Settings {
+        refresh_interval: options.update,
+        idle: options.idle,
+        masters: options.masters.split(',').filter(|m| !m.is_empty()).map(String::from).collect(),
+        connection_manager: options.connection_manager,
+        record: options.record,
+        replay: options.replay,
+        history: options.history,
+        slow_threshold: options.slow_threshold,
+        slow_log: options.slow_log,
+        skew_threshold: options.skew_threshold,
+        skew_refreshes: options.skew_refreshes,
+        filters: options
+            .filter
+            .iter()
+            .map(|f| ybtop::filter::parse_filters(f))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            })
+            .concat(),
+        color: !options.no_color && ybtop::syntax::use_color(),
+        time_colors: options.time_colors,
+        login: ybtop::ysql::Login {
+            port: options.ysql_port,
+            user: options.ysql_user,
+            password: std::env::var("PGPASSWORD").ok(),
+        },
+        ysql_database: options.ysql_database,
+        locks: options.locks,
+        pg_stat_activity: options.pg_stat_activity,
+    };
 
-    ybtop::display_clients( hostname_vec, port_vec, update_interval, idle );
+    ybtop::display_clients( hostname_vec, port_vec, settings );
 
 }
@@ -0,0 +1,179 @@
+//! Recording the pages read in every refresh to disk, and replaying them.
+//!
+//! A recording is a directory with a directory per refresh, named by the time of the refresh
+//! in milliseconds since the epoch, holding the pages read in that refresh, and the errors of the
+//! pages that couldn't be read, as named by [`FileSource`].
+use crate::error::Error;
+use crate::snapshot::Endpoint;
+use crate::source::{FileSource, Schedule, Source};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Reads the pages from another source, and writes every page it reads to the recording.
+pub struct RecordingSource {
+    source: Box<dyn Source>,
+    directory: PathBuf,
+    refresh_directory: PathBuf,
+}
+
+impl RecordingSource {
+    pub fn new(source: Box<dyn Source>, directory: impl Into<PathBuf>) -> io::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        Ok(RecordingSource {
+            source,
+            refresh_directory: directory.clone(),
+            directory,
+        })
+    }
+}
+
+impl Source for RecordingSource {
+    fn fetch(&mut self, endpoint: &Endpoint, path: &str) -> Result<String, Error> {
+        let result = self.source.fetch(endpoint, path);
+        fs::create_dir_all(&self.refresh_directory)?;
+        match &result {
+            Ok(http_data) => fs::write(self.refresh_directory.join(FileSource::file_name(endpoint, path)), http_data)?,
+            Err(e) => fs::write(
+                self.refresh_directory.join(FileSource::error_file_name(endpoint, path)),
+                FileSource::error_text(e),
+            )?,
+        }
+        result
+    }
+
+    fn begin_refresh(&mut self) -> SystemTime {
+        let timestamp = self.source.begin_refresh();
+        let millis = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        self.refresh_directory = self.directory.join(millis.to_string());
+        // a refresh in which no endpoint answered is recorded as an empty directory.
+        // when it can't be created, fetch reports the error.
+        let _ = fs::create_dir_all(&self.refresh_directory);
+        timestamp
+    }
+
+    fn schedule(&self) -> Schedule {
+        self.source.schedule()
+    }
+
+    fn position(&self) -> Option<(usize, usize)> {
+        self.source.position()
+    }
+}
+
+/// Replays a recording, one recorded refresh per refresh.
+pub struct ReplaySource {
+    refreshes: Vec<(SystemTime, FileSource)>,
+    /// the refresh that is replayed, None before the first.
+    position: Option<usize>,
+}
+
+impl ReplaySource {
+    pub fn new(directory: &Path) -> io::Result<Self> {
+        let mut refreshes = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+            if let Some(millis) = entry.file_name().to_str().and_then(|name| name.parse::<u64>().ok()) {
+                refreshes.push((UNIX_EPOCH + Duration::from_millis(millis), FileSource::new(entry.path())));
+            }
+        }
+        if refreshes.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no recorded refreshes in {}", directory.display()),
+            ));
+        }
+        refreshes.sort_by_key(|(timestamp, _)| *timestamp);
+        Ok(ReplaySource {
+            refreshes,
+            position: None,
+        })
+    }
+
+    /// The endpoints that have a page, such as /rpcz, in any of the recorded refreshes.
+    pub fn endpoints(&self, path: &str) -> io::Result<Vec<Endpoint>> {
+        let mut endpoints = BTreeSet::new();
+        for (_, refresh) in &self.refreshes {
+            endpoints.extend(refresh.endpoints(path)?.into_iter().map(|e| (e.hostname, e.port)));
+        }
+        Ok(endpoints.into_iter().map(|(hostname, port)| Endpoint::new(&hostname, &port)).collect())
+    }
+}
+
+impl Source for ReplaySource {
+    fn fetch(&mut self, endpoint: &Endpoint, path: &str) -> Result<String, Error> {
+        match self.position {
+            Some(position) => self.refreshes[position].1.fetch(endpoint, path),
+            None => Err(Error::NotReachable(endpoint.to_string())),
+        }
+    }
+
+    fn begin_refresh(&mut self) -> SystemTime {
+        // past the last refresh, the last one is replayed again.
+        let position = self.position.map_or(0, |p| (p + 1).min(self.refreshes.len() - 1));
+        self.position = Some(position);
+        self.refreshes[position].0
+    }
+
+    fn schedule(&self) -> Schedule {
+        let next = self.position.map_or(0, |p| p + 1);
+        match self.refreshes.get(next) {
+            Some((timestamp, _)) => Schedule::Next(*timestamp),
+            None => Schedule::Finished,
+        }
+    }
+
+    fn position(&self) -> Option<(usize, usize)> {
+        Some((self.position.map_or(0, |p| p + 1), self.refreshes.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::Collector;
+    use crate::source::ScriptedSource;
+
+    #[test]
+    fn record_and_replay() {
+        let directory = std::env::temp_dir().join(format!("ybtop_recording_{}", std::process::id()));
+        let ysql = Endpoint::new("192.168.66.80", "13000");
+        let active = r#"{"connections": [{"process_start_time": "2022-03-27 15:55:22.587029+00", "application_name": "ysqlsh", "backend_type": "client backend", "backend_status": "active", "db_name": "yugabyte", "host": "127.0.0.1", "port": "50736", "query": "select pg_sleep(120);", "query_running_for_ms": 7466}]}"#;
+        let mut source = ScriptedSource::new();
+        source.push(&ysql, "/rpcz", Ok(active.to_string()));
+        source.push(&ysql, "/rpcz", Err(Error::Timeout(ysql.to_string())));
+
+        let mut recording = Collector::with_source(
+            vec![ysql.clone()],
+            Box::new(RecordingSource::new(Box::new(source), &directory).unwrap()),
+        );
+        let first = recording.collect().unwrap();
+        // the refresh directories are named by millisecond.
+        std::thread::sleep(Duration::from_millis(2));
+        let second = recording.collect().unwrap();
+
+        let replay = ReplaySource::new(&directory).unwrap();
+        assert_eq!(replay.position(), Some((0, 2)));
+        // the endpoints are taken from the recording.
+        let endpoints = replay.endpoints("/rpcz").unwrap();
+        assert_eq!(endpoints, vec![ysql]);
+        let mut replaying = Collector::with_source(endpoints, Box::new(replay));
+        let replayed = replaying.collect().unwrap();
+        assert_eq!(replayed.sessions(false).len(), 1);
+        assert_eq!(replayed.sessions(false)[0].query, "select pg_sleep(120);");
+        assert_eq!(
+            replayed.timestamp.duration_since(UNIX_EPOCH).unwrap().as_millis(),
+            first.timestamp.duration_since(UNIX_EPOCH).unwrap().as_millis()
+        );
+        assert!(matches!(replaying.schedule(), Schedule::Next(_)));
+        let replayed = replaying.collect().unwrap();
+        assert!(replayed.sessions(false).is_empty());
+        assert_eq!(replayed.endpoints[0].error.as_deref(), Some("192.168.66.80:13000 timed out"));
+        assert_eq!(replayed.endpoints[0].error, second.endpoints[0].error);
+        assert!(matches!(replaying.schedule(), Schedule::Finished));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
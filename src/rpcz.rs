@@ -0,0 +1,748 @@
+//! The /rpcz output of the YSQL, YCQL, YEDIS and tserver web servers.
+use self::AllConnections::{Connections, InboundConnections};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A /rpcz output: YSQL has connections, the other APIs and the tserver have inbound connections.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum AllConnections {
+    Connections {
+        connections: Vec<Connection>,
+    },
+    InboundConnections {
+        inbound_connections: Vec<InboundConnection>,
+    },
+    Empty {},
+}
+
+// The /rpcz of every version is read into the same structs: the fields a release renamed are put
+// under the names ybtop reads by RENAMED_FIELDS for the version of the node, the fields ybtop needs
+// are taken with a default when a release drops them, and the fields it doesn't know are kept in
+// extra, so a changed /rpcz doesn't stop ybtop, and the values of those fields are shown in the detail view.
+const CONNECTION_FIELDS: [&str; 4] = ["process_start_time", "application_name", "backend_type", "backend_status"];
+const INBOUND_CONNECTION_FIELDS: [&str; 3] = ["remote_ip", "state", "processed_call_count"];
+const CALLS_IN_FLIGHT_FIELDS: [&str; 1] = ["elapsed_millis"];
+
+/// The fields that releases renamed: the list of objects they are in, the first version with the new
+/// name, the new name and the name ybtop reads.
+const RENAMED_FIELDS: [(&str, &str, &str, &str); 1] = [("connections", "2.25.0.0", "backend_state", "backend_status")];
+
+/// A YSQL backend.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Connection {
+    pub process_start_time: String,
+    pub application_name: String,
+    pub backend_type: String,
+    /// absent when a release renamed it to a name ybtop doesn't know.
+    pub backend_status: Option<String>,
+    pub db_oid: Option<u32>,
+    pub db_name: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<String>,
+    pub query: Option<String>,
+    pub query_start_time: Option<String>,
+    pub transaction_start_time: Option<String>,
+    pub process_running_for_ms: Option<u32>,
+    pub transaction_running_for_ms: Option<u32>,
+    pub query_running_for_ms: Option<u32>,
+    /// the wait event of the backend, in the /rpcz of newer versions.
+    pub wait_event_type: Option<String>,
+    pub wait_event: Option<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// A YCQL, YEDIS or tserver client connection.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct InboundConnection {
+    pub remote_ip: String,
+    pub state: String,
+    pub processed_call_count: u32,
+    pub connection_details: Option<ConnectionDetails>,
+    pub calls_in_flight: Option<Vec<CallsInFlight>>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ConnectionDetails {
+    pub cql_connection_details: CqlConnectionDetails,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct CqlConnectionDetails {
+    pub keyspace: String,
+}
+
+/// A call on an inbound connection: it has cql details, redis details, or for the tserver a request header.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct CallsInFlight {
+    pub elapsed_millis: u32,
+    pub cql_details: Option<CqlDetails>,
+    pub redis_details: Option<RedisDetails>,
+    pub header: Option<RequestHeader>,
+    pub trace_buffer: Option<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct RedisDetails {
+    pub call_details: Vec<RedisCallDetails>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct RedisCallDetails {
+    pub redis_string: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RequestHeader {
+    pub call_id: Option<u64>,
+    pub remote_method: Option<RemoteMethod>,
+    pub timeout_millis: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RemoteMethod {
+    pub service_name: String,
+    pub method_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct CqlDetails {
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub call_details: Vec<CallDetails>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CallDetails {
+    pub sql_id: Option<String>,
+    pub sql_string: String,
+    pub params: Option<String>,
+}
+
+/// The /api/v1/version output of a yugabyte web server.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ServerVersion {
+    pub version_number: String,
+    pub build_number: String,
+    pub build_type: Option<String>,
+}
+
+/// Parse a /rpcz output. An endpoint without connections parses as Empty.
+pub fn parse_result(http_data: String) -> Result<AllConnections, serde_json::Error> {
+    serde_json::from_str(&http_data)
+}
+
+/// Parse the /rpcz output of a node of a version, such as 2.13.0.0-b42, with the fields its release
+/// renamed under the names ybtop reads, and tell the fields ybtop needs that it doesn't have. When the
+/// version isn't known, a renamed field is read when the /rpcz doesn't have the name ybtop reads.
+pub fn parse_result_of_version(
+    http_data: &str,
+    version: Option<&str>,
+) -> Result<(AllConnections, BTreeSet<String>), serde_json::Error> {
+    let mut value: Value = serde_json::from_str(http_data)?;
+    for (objects, since, renamed, field) in RENAMED_FIELDS {
+        if version.is_some_and(|version| !version_at_least(version, since)) {
+            continue;
+        }
+        let objects = value.get_mut(objects).and_then(Value::as_array_mut).into_iter().flatten();
+        for object in objects.filter_map(Value::as_object_mut) {
+            if !object.contains_key(field) {
+                if let Some(field_value) = object.remove(renamed) {
+                    object.insert(field.to_string(), field_value);
+                }
+            }
+        }
+    }
+    let missing = missing_fields_of(&value);
+    Ok((serde_json::from_value(value)?, missing))
+}
+
+/// Whether a version, such as 2.13.0.0-b42, is since or later, comparing the numbers of both.
+fn version_at_least(version: &str, since: &str) -> bool {
+    let numbers = |version: &str| -> Vec<u32> {
+        version.split(['.', '-']).map_while(|part| part.trim_start_matches('b').parse().ok()).collect()
+    };
+    numbers(version) >= numbers(since)
+}
+
+/// The fields ybtop needs that are absent in a /rpcz output, which means the release renamed or dropped them.
+pub fn missing_fields(http_data: &str) -> BTreeSet<String> {
+    serde_json::from_str::<Value>(http_data).map(|value| missing_fields_of(&value)).unwrap_or_default()
+}
+
+fn missing_fields_of(value: &Value) -> BTreeSet<String> {
+    fn check(objects: Option<&Value>, fields: &[&str], missing: &mut BTreeSet<String>) {
+        for object in objects.and_then(Value::as_array).into_iter().flatten() {
+            for field in fields {
+                if object.get(field).is_none() {
+                    missing.insert(field.to_string());
+                }
+            }
+            check(object.get("calls_in_flight"), &CALLS_IN_FLIGHT_FIELDS, missing);
+        }
+    }
+    let mut missing = BTreeSet::new();
+    check(value.get("connections"), &CONNECTION_FIELDS, &mut missing);
+    check(value.get("inbound_connections"), &INBOUND_CONNECTION_FIELDS, &mut missing);
+    missing
+}
+
+/// The fields in a /rpcz output that ybtop doesn't know, kept in the extra maps.
+pub fn unknown_fields(all_connections: &AllConnections) -> BTreeSet<String> {
+    let mut unknown = BTreeSet::new();
+    match all_connections {
+        Connections { connections } => {
+            for connection in connections {
+                unknown.extend(connection.extra.keys().cloned());
+            }
+        }
+        InboundConnections { inbound_connections } => {
+            for connection in inbound_connections {
+                unknown.extend(connection.extra.keys().cloned());
+                for cif in connection.calls_in_flight.iter().flatten() {
+                    unknown.extend(cif.extra.keys().cloned());
+                }
+            }
+        }
+        _ => {}
+    }
+    unknown
+}
+
+/// A redis call is shown as the command with its arguments, the key is the first argument.
+pub fn redis_command_and_key(redis_string: &str) -> (String, String) {
+    let mut words = redis_string.split_whitespace();
+    let command = words.next().unwrap_or_default().to_uppercase();
+    let key = words.next().unwrap_or_default().to_string();
+    (command, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ysql_checkpointer() {
+        let http_result = r#"
+{
+    "connections": [
+        {
+            "process_start_time": "2022-03-27 10:28:59.884701+00",
+            "application_name": "",
+            "backend_type": "checkpointer",
+            "backend_status": ""
+        }
+    ]
+}
+        "#;
+        let result = parse_result(http_result.to_string()).unwrap();
+        let from_enum = match result {
+            Connections { connections } => {
+                connections[0].backend_type.clone()
+            },
+            _ => String::from("")
+        };
+        assert_eq!(from_enum, "checkpointer");
+    }
+
+    #[test]
+    fn parse_ysql_idle_process() {
+        let http_result = r#"
+{
+    "connections": [
+            {
+            "db_oid": 13281,
+            "db_name": "yugabyte",
+            "query": "select pg_sleep(120);",
+            "process_start_time": "2022-03-27 15:55:22.587029+00",
+            "query_start_time": "2022-03-28 12:32:39.503558+00",
+            "application_name": "ysqlsh",
+            "backend_type": "client backend",
+            "backend_status": "idle",
+            "host": "127.0.0.1",
+            "port": "50736"
+        }
+    ]
+}
+        "#;
+        let result = parse_result(http_result.to_string()).unwrap();
+        let from_enum = match result {
+            Connections { connections } => {
+                connections[0].backend_type.clone()
+            },
+            _ => String::from("")
+        };
+        assert_eq!(from_enum, "client backend");
+    }
+
+    #[test]
+    fn parse_ysql_active_process() {
+        let http_result = r#"
+{
+    "connections": [
+        {
+            "db_oid": 13281,
+            "db_name": "yugabyte",
+            "query": "select pg_sleep(120);",
+            "process_start_time": "2022-03-27 15:55:22.587029+00",
+            "process_running_for_ms": 76583532,
+            "transaction_start_time": "2022-03-28 13:11:38.653675+00",
+            "transaction_running_for_ms": 7466,
+            "query_start_time": "2022-03-28 13:11:38.653675+00",
+            "query_running_for_ms": 7466,
+            "application_name": "ysqlsh",
+            "backend_type": "client backend",
+            "backend_status": "active",
+            "host": "127.0.0.1",
+            "port": "50736"
+        }
+    ]
+}
+        "#;
+        let result = parse_result(http_result.to_string()).unwrap();
+        let from_enum = match result {
+            Connections { connections } => {
+                connections[0].backend_type.clone()
+            },
+            _ => String::from("")
+        };
+        assert_eq!(from_enum, "client backend");
+    }
+
+    #[test]
+    // the baseline test, kept as it was: any result is taken as no connections.
+    #[allow(clippy::match_single_binding)]
+    fn parse_ycql_no_connections() {
+        let http_result = r#"
+{}
+        "#;
+        let result = parse_result(http_result.to_string());
+        let from_enum = match result {
+            _ => String::from("Empty")
+        };
+        assert_eq!(from_enum, "Empty");
+    }
+
+    #[test]
+    fn parse_ycql_idle_connections() {
+        let http_result = r#"
+{
+    "inbound_connections": [
+        {
+            "remote_ip": "127.0.0.1:35518",
+            "state": "OPEN",
+            "processed_call_count": 2
+        },
+        {
+            "remote_ip": "127.0.0.1:35516",
+            "state": "OPEN",
+            "processed_call_count": 13
+        }
+    ]
+}
+        "#;
+        let result = parse_result(http_result.to_string()).unwrap();
+        let count_connections = match result {
+            InboundConnections { ref inbound_connections} => {
+                inbound_connections.len()
+            },
+            _ => 0
+        };
+        assert_eq!(count_connections, 2);
+        let remote_ip = match result {
+            InboundConnections { inbound_connections} => {
+                inbound_connections[0].remote_ip.clone()
+            },
+            _ => String::from("")
+        };
+        assert_eq!(remote_ip, "127.0.0.1:35518");
+    }
+
+    #[test]
+    fn parse_ycql_query() {
+        let http_result = r#"
+{
+    "inbound_connections": [
+        {
+            "remote_ip": "127.0.0.1:35518",
+            "state": "OPEN",
+            "processed_call_count": 20,
+            "connection_details": {
+                "cql_connection_details": {
+                    "keyspace": "cr"
+                }
+            },
+            "calls_in_flight": [
+                {
+                    "elapsed_millis": 252,
+                    "cql_details": {
+                        "type": "QUERY",
+                        "call_details": [
+                            {
+                                "sql_string": "select avg(permit), avg(permit_recheck), avg( handgun), avg( long_gun), avg( other), avg( multiple), avg( admin), avg( prepawn_handgun), avg( prepawn_long_gun), avg( prepawn_other), avg( redemption_handgun), avg( redemption_long_gun), avg( redemption_other), avg( returned_handgun), avg( returned_long_gun), avg( returned_other), avg( rentals_handgun), avg( rentals_long_gun), avg( private_sale_handgun), avg( private_sale_long_gun), avg( private_sale_other), avg( return_to_seller_handgun), avg( return_to_seller_long_gun), avg( return_to_seller_other), avg( totals) from fa_bg_checks;"
+                            }
+                        ]
+                    }
+                }
+            ]
+        }
+    ]
+}
+        "#;
+        let result = parse_result(http_result.to_string()).unwrap();
+        let remote_ip = match result {
+            InboundConnections { ref inbound_connections} => {
+                inbound_connections[0].remote_ip.clone()
+            },
+            _ => String::from("")
+        };
+        assert_eq!(remote_ip, "127.0.0.1:35518");
+        let keyspace = match result {
+            InboundConnections { ref inbound_connections} => {
+                match &inbound_connections[0].connection_details {
+                    Some(details) => {
+                        details.cql_connection_details.keyspace.clone()
+                    },
+                    None => String::from("")
+                }
+            },
+            _ => String::from("")
+        };
+        assert_eq!(keyspace, "cr");
+        let sql_string = match result {
+            InboundConnections { ref inbound_connections} => {
+                match &inbound_connections[0].calls_in_flight {
+                    Some(cif) => {
+                        cif[0].cql_details.as_ref().unwrap().call_details[0].sql_string.clone()
+                    },
+                    None => String::from("")
+                }
+            },
+            _ => String::from("")
+        };
+        assert_eq!(sql_string, "select avg(permit), avg(permit_recheck), avg( handgun), avg( long_gun), avg( other), avg( multiple), avg( admin), avg( prepawn_handgun), avg( prepawn_long_gun), avg( prepawn_other), avg( redemption_handgun), avg( redemption_long_gun), avg( redemption_other), avg( returned_handgun), avg( returned_long_gun), avg( returned_other), avg( rentals_handgun), avg( rentals_long_gun), avg( private_sale_handgun), avg( private_sale_long_gun), avg( private_sale_other), avg( return_to_seller_handgun), avg( return_to_seller_long_gun), avg( return_to_seller_other), avg( totals) from fa_bg_checks;");
+    }
+
+    #[test]
+    // the params of the captured /rpcz are binary, and one of them holds a soft hyphen.
+    #[allow(clippy::invisible_characters)]
+    fn parse_ycql_batch() {
+        let http_result = r#"
+{
+    "inbound_connections": [
+        {
+            "remote_ip": "127.0.0.1:35692",
+            "state": "OPEN",
+            "processed_call_count": 135,
+            "connection_details": {
+                "cql_connection_details": {
+                    "keyspace": "cr"
+                }
+            },
+            "calls_in_flight": [
+                {
+                    "elapsed_millis": 6,
+                    "cql_details": {
+                        "type": "BATCH",
+                        "call_details": [
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Alabama, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u001C,, \u0000\u0000\u001C\u001C, n/a, \u0000\u0000\u0001B, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\t, \u0000\u0000\u0000\u000B, n/a, \u0000\u0000\u0005\u000F, \u0000\u0000\u0005, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000D.]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Alaska, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0005ó, \u0000\u0000\u0007l, n/a, \u0000\u0000\u0000L, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0006, \u0000\u0000\u0000\f, n/a, \u0000\u0000\u0000|, \u0000\u0000\u0000£, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000\u000EÜ]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Arizona, \u0000\u0000\nG, n/a, \u0000\u0000\u0015_, \u0000\u0000\u0010Í, n/a, \u0000\u0000\u0000å, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0004, \u0000\u0000\u0000\u0002, n/a, \u0000\u0000\u0002-, \u0000\u0000\u0001Á, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u00005L]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Arkansas, \u0000\u0000\tÖ, n/a, \u0000\u0000\f­, \u0000\u0000\r\", n/a, \u0000\u0000\u0000Î, \u0000\u0000\u0000\u0002, \u0000\u0000\u0000\u0006, \u0000\u0000\u0000\u0013, n/a, \u0000\u0000\u00022, \u0000\u0000\u0005j, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000,*]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, California, \u0000\u0000^ö, n/a, \u0000\u0000F‘, \u0000\u0000:c, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000ßê]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Colorado, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u001FQ, \u0000\u0000\u001E‘, n/a, \u0000\u0000\fM, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000J/]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Connecticut, \u0000\u0000\u0014ñ, n/a, \u0000\u0000\tú, \u0000\u0000\bK, n/a, \u0000\u0000\u00009, \u0000\u0000\u00023, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000)¢]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Delaware, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0002\u000B, \u0000\u0000\u0001Ü, n/a, \u0000\u0000\u0000\u000E, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000\u0003õ]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, District of Columbia, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0010, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000\u0000\u0010]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Florida, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000SE, \u0000\u0000*G, n/a, \u0000\u0000\u0002Ù, \u0000\u0000\u0001`, \u0000\u0000\u0000\u0005, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0002\u0004, \u0000\u0000\u0001“, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000…a]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Georgia, \u0000\u0000!D, n/a, \u0000\u0000\u001A), \u0000\u0000\u0012q, n/a, \u0000\u0000\u00010, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0019, \u0000\u0000\u0000\u001F, n/a, \u0000\u0000\u0003Œ, \u0000\u0000\u0005\u001F, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000Wñ]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Guam, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\n, \u0000\u0000\u0000\u0019, n/a, \u0000\u0000\u0000\u0001, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000\u0000$]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Hawaii, \u0000\u0000\u0002`, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000\u0002`]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Idaho, \u0000\u0000\u0005„, n/a, \u0000\u0000\u0006[, \u0000\u0000\u000Bk, n/a, \u0000\u0000\u0000c, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0001, \u0000\u0000\u0000\u0007, n/a, \u0000\u0000\u0000Ç, \u0000\u0000\u0001þ, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000\u001Az]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Illinois, \u0000\u0000d;, n/a, \u0000\u0000\u0014l, \u0000\u0000\u0014l, n/a, \u0000\u0000\u0001\u0007, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000Ž\u001A]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Indiana, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0016E, \u0000\u0000\u0016:, n/a, \u0000\u0000\u0000É, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0001, n/a, \u0000\u0000\u0000\u0001, \u0000\u0000\u0001„, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000.Î]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Iowa, \u0000\u0000\r\r, n/a, \u0000\u0000\u0000\u0011, \u0000\u0000\u0006, n/a, \u0000\u0000\u0000\u0001, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0001, n/a, \u0000\u0000\u0000\u0002, \u0000\u0000\u0000N, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000\u0013ï]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Kansas, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\fÜ, \u0000\u0000\u000B#, n/a, \u0000\u0000\u0000¬, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0001, \u0000\u0000\u0000\u0002, n/a, \u0000\u0000\u0001), \u0000\u0000\u0001\b, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0000\u001Aß]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Kentucky, \u0000\u0002\u0006K, n/a, \u0000\u0000\u0016@, \u0000\u0000\u0013ª, n/a, \u0000\u0000\u0001u, \u0000\u0000\u0000\u0002, \u0000\u0000\u0000\u0010, \u0000\u0000\u0000\f, n/a, \u0000\u0000\u0004`, \u0000\u0000\u0006o, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u0002<—]"
+                            },
+                            {
+                                "sql_id": "344cf13216c84b621b82d4c212f04b0a",
+                                "sql_string": "INSERT INTO cr.fa_bg_checks (year_month, state, permit, permit_recheck, handgun, long_gun, other, multiple, admin, prepawn_handgun, prepawn_long_gun, prepawn_other, redemption_handgun, redemption_long_gun, redemption_other, returned_handgun, returned_long_gun, returned_other, rentals_handgun, rentals_long_gun, private_sale_handgun, private_sale_long_gun, private_sale_other, return_to_seller_handgun, return_to_seller_long_gun, return_to_seller_other, totals) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                "params": "[2008-06, Louisiana, \u0000\u0000\u0000\u0000, n/a, \u0000\u0000\u0019Í, \u0000\u0000\u0014L, n/a, \u0000\u0000\u0000Ú, \u0000\u0000\u0000\u0000, \u0000\u0000\u0000\u0005, \u0000\u0000\u0000\u0003, n/a, \u0000\u0000\u0002ƒ, \u0000\u0000\u0003@, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, n/a, \u0000\u00004¾]"
+                            }
+                        ]
+                    }
+                }
+            ]
+        }
+    ]
+}
+        "#;
+        let result = parse_result(http_result.to_string()).unwrap();
+        let remote_ip = match result {
+            InboundConnections { ref inbound_connections} => {
+                inbound_connections[0].remote_ip.clone()
+            },
+            _ => String::from("")
+        };
+        assert_eq!(remote_ip, "127.0.0.1:35692");
+        let keyspace = match result {
+            InboundConnections { ref inbound_connections} => {
+                match &inbound_connections[0].connection_details {
+                    Some(details) => {
+                        details.cql_connection_details.keyspace.clone()
+                    },
+                    None => String::from("")
+                }
+            },
+            _ => String::from("")
+        };
+        assert_eq!(keyspace, "cr");
+        let call_details = match result {
+            InboundConnections { ref inbound_connections} => {
+                match &inbound_connections[0].calls_in_flight {
+                    Some(cif) => {
+                        cif[0].cql_details.as_ref().unwrap().call_details.clone()
+                    },
+                    None => Vec::<CallDetails>::new()
+                }
+            },
+            _ => Vec::<CallDetails>::new()
+        };
+        assert_eq!(call_details.len(), 20);
+    }
+
+    #[test]
+    fn parse_tserver_calls_in_flight() {
+        let http_result = r#"
+{
+    "inbound_connections": [
+        {
+            "remote_ip": "192.168.66.81:43127",
+            "state": "OPEN",
+            "processed_call_count": 8812,
+            "calls_in_flight": [
+                {
+                    "header": {
+                        "call_id": 81234,
+                        "remote_method": {
+                            "service_name": "yb.tserver.TabletServerService",
+                            "method_name": "Read"
+                        },
+                        "timeout_millis": 60000
+                    },
+                    "elapsed_millis": 1530,
+                    "trace_buffer": "0328 13:11:38.653675 (+     0us) service_pool.cc:147] Inserting onto call queue\n0328 13:11:38.653701 (+    26us) tablet_service.cc:1940] T 7bb48b1e4a8c4bbb9e4ffa8e4c2a1e0d P 0a1b2c3d4e5f40718293a4b5c6d7e8f9: Start Read\n"
+                }
+            ]
+        }
+    ]
+}
+        "#;
+        let result = parse_result(http_result.to_string()).unwrap();
+        let (method_name, tablet_id) = match result {
+            InboundConnections { ref inbound_connections} => {
+                let cif = &inbound_connections[0].calls_in_flight.as_ref().unwrap()[0];
+                assert!(cif.cql_details.is_none());
+                (
+                    cif.header.as_ref().unwrap().remote_method.as_ref().unwrap().method_name.clone(),
+                    cif.trace_buffer.as_deref().and_then(crate::tablets::tablet_id_from_trace),
+                )
+            },
+            _ => (String::from(""), None)
+        };
+        assert_eq!(method_name, "Read");
+        assert_eq!(tablet_id.as_deref(), Some("7bb48b1e4a8c4bbb9e4ffa8e4c2a1e0d"));
+    }
+
+    #[test]
+    fn parse_yedis_call() {
+        let http_result = r#"
+{
+    "inbound_connections": [
+        {
+            "remote_ip": "127.0.0.1:52344",
+            "state": "OPEN",
+            "processed_call_count": 311,
+            "calls_in_flight": [
+                {
+                    "elapsed_millis": 12,
+                    "redis_details": {
+                        "call_details": [
+                            {
+                                "redis_string": "hset session:4711 last_seen 1648472398"
+                            }
+                        ]
+                    }
+                }
+            ]
+        }
+    ]
+}
+        "#;
+        let result = parse_result(http_result.to_string()).unwrap();
+        let redis_string = match result {
+            InboundConnections { ref inbound_connections} => {
+                match &inbound_connections[0].calls_in_flight {
+                    Some(cif) => {
+                        cif[0].redis_details.as_ref().unwrap().call_details[0].redis_string.clone()
+                    },
+                    None => String::from("")
+                }
+            },
+            _ => String::from("")
+        };
+        assert_eq!(redis_string, "hset session:4711 last_seen 1648472398");
+        let (command, key) = redis_command_and_key(&redis_string);
+        assert_eq!(command, "HSET");
+        assert_eq!(key, "session:4711");
+    }
+
+    #[test]
+    fn parse_ysql_changed_fields() {
+        let http_result = r#"
+{
+    "connections": [
+        {
+            "db_oid": 13281,
+            "db_name": "yugabyte",
+            "query": "select pg_sleep(120);",
+            "process_start_time": "2022-03-27 15:55:22.587029+00",
+            "query_running_for_ms": 7466,
+            "application_name": "ysqlsh",
+            "backend_type": "client backend",
+            "backend_state": "active",
+            "wait_event": "PgSleep",
+            "host": "127.0.0.1",
+            "port": "50736"
+        }
+    ]
+}
+        "#;
+        assert_eq!(
+            missing_fields(http_result),
+            BTreeSet::from([String::from("backend_status")])
+        );
+        let result = parse_result(http_result.to_string()).unwrap();
+        assert_eq!(
+            unknown_fields(&result),
+            BTreeSet::from([String::from("backend_state")])
+        );
+        let wait_event = match result {
+            Connections { connections } => {
+                connections[0].wait_event.clone().unwrap_or_default()
+            },
+            _ => String::from("")
+        };
+        assert_eq!(wait_event, "PgSleep");
+    }
+
+    #[test]
+    fn parse_renamed_fields_of_version() {
+        let http_result = r#"{"connections": [{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_state": "active"}]}"#;
+        let status = |version: Option<&str>| match parse_result_of_version(http_result, version).unwrap().0 {
+            Connections { connections } => connections[0].backend_status.clone(),
+            _ => None,
+        };
+        assert_eq!(status(Some("2.25.1.0-b12")).as_deref(), Some("active"));
+        assert_eq!(status(None).as_deref(), Some("active"));
+        assert_eq!(status(Some("2.13.0.0-b42")), None);
+        assert!(version_at_least("2.25.0.0-b1", "2.25.0.0"));
+        assert!(!version_at_least("2.9.0.0", "2.25.0.0"));
+    }
+
+    #[test]
+    fn parse_invalid_json() {
+        assert!(parse_result(String::from("<html>not found</html>")).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! Searching the query text of the sessions, and the statements of YCQL batches, with a regex.
+use crate::snapshot::GeneralPresentation;
+use crate::syntax::{self, Span};
+use crate::tracking::{row_key, RowKey, TrackedRow};
+use regex::{Regex, RegexBuilder};
+
+/// Start and end of a highlighted match, as ANSI reverse video.
+const HIGHLIGHT_ON: &str = "\x1b[7m";
+const HIGHLIGHT_OFF: &str = "\x1b[27m";
+
+pub struct Search {
+    regex: Regex,
+    /// hide the rows that don't match.
+    pub hide: bool,
+}
+
+impl Search {
+    /// A search for a regex, ignoring case.
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Search {
+            regex: RegexBuilder::new(pattern).case_insensitive(true).build()?,
+            hide: false,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    pub fn matches(&self, row: &GeneralPresentation) -> bool {
+        self.regex.is_match(&row.query) || row.statements.iter().any(|s| self.regex.is_match(s))
+    }
+
+    /// The query of a row, and for a batch of which the query only tells the number of statements,
+    /// the first matching statement.
+    pub fn text(&self, row: &GeneralPresentation) -> String {
+        match row.statements.iter().find(|s| self.regex.is_match(s)) {
+            Some(statement) => format!("{}: {}", row.query, statement),
+            None => row.query.clone(),
+        }
+    }
+
+    /// The spans that highlight the matches in a text.
+    pub fn spans(&self, text: &str) -> Vec<Span> {
+        self.regex
+            .find_iter(text)
+            .map(|m| (m.start(), m.end(), HIGHLIGHT_ON, HIGHLIGHT_OFF))
+            .collect()
+    }
+
+    /// The text with its matches highlighted.
+    pub fn highlight(&self, text: &str) -> String {
+        syntax::paint(text, &self.spans(text))
+    }
+}
+
+/// The key of the next matching row after the selected one, or before it going backward,
+/// starting over at the other end.
+pub fn next_match(rows: &[TrackedRow], selected: Option<&RowKey>, search: &Search, forward: bool) -> Option<RowKey> {
+    let mut keys: Vec<(RowKey, bool)> = rows.iter().map(|r| (row_key(&r.row), search.matches(&r.row))).collect();
+    if !forward {
+        keys.reverse();
+    }
+    let start = selected
+        .and_then(|selected| keys.iter().position(|(key, _)| key == selected))
+        .map_or(0, |position| position + 1);
+    keys.iter()
+        .cycle()
+        .skip(start)
+        .take(keys.len())
+        .find(|(_, matches)| *matches)
+        .map(|(key, _)| key.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::RowState;
+
+    fn row(call: &str, query: &str, statements: &[&str]) -> TrackedRow {
+        TrackedRow {
+            row: GeneralPresentation {
+                api: String::from("YCQL"),
+                server: String::from("192.168.66.80"),
+                client: String::from("10.1.0.5:35518"),
+                keyspace_db_name: String::from("cr"),
+                status: String::from("QUERY"),
+                query_time_ms: 100,
+                query: query.to_string(),
+                statements: statements.iter().map(|s| s.to_string()).collect(),
+                session: String::from("192.168.66.80 10.1.0.5:35518"),
+                call: call.to_string(),
+                ..Default::default()
+            },
+            state: RowState::Continuing,
+            statements_per_second: None,
+        }
+    }
+
+    #[test]
+    fn search_and_jump() {
+        let rows = vec![
+            row("1", "select * from orders", &[]),
+            row("2", "select * from customers", &[]),
+            row("3", "Number of statements: 2", &["insert into customers (id) values (1)", "insert into ORDERS (id) values (1)"]),
+        ];
+        let search = Search::new("orders").unwrap();
+        assert!(search.matches(&rows[0].row));
+        assert!(!search.matches(&rows[1].row));
+        assert!(search.matches(&rows[2].row));
+        assert_eq!(
+            search.highlight(&search.text(&rows[2].row)),
+            "Number of statements: 2: insert into \x1b[7mORDERS\x1b[27m (id) values (1)"
+        );
+
+        let first = next_match(&rows, None, &search, true).unwrap();
+        assert_eq!(first.1, "1");
+        let second = next_match(&rows, Some(&first), &search, true).unwrap();
+        assert_eq!(second.1, "3");
+        assert_eq!(next_match(&rows, Some(&second), &search, true).unwrap().1, "1");
+        assert_eq!(next_match(&rows, Some(&first), &search, false).unwrap().1, "3");
+        assert!(next_match(&rows, None, &Search::new("nothing").unwrap(), true).is_none());
+        assert!(Search::new("(").is_err());
+    }
+}
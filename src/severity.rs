@@ -0,0 +1,108 @@
+//! Colors that make the rows that matter stand out: the time of a query by its age, and the status
+//! of a session, such as a YSQL session idle in a transaction or a YCQL batch.
+use std::time::Duration;
+
+/// Escape sequences to start and end a style.
+pub type Style = (&'static str, &'static str);
+
+const YELLOW: Style = ("\x1b[33m", "\x1b[39m");
+const RED: Style = ("\x1b[31m", "\x1b[39m");
+const BLINKING_RED: Style = ("\x1b[5;31m", "\x1b[25;39m");
+const GREEN: Style = ("\x1b[32m", "\x1b[39m");
+const MAGENTA: Style = ("\x1b[35m", "\x1b[39m");
+const CYAN: Style = ("\x1b[36m", "\x1b[39m");
+
+/// From which time a query is shown yellow, red and blinking red.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub yellow: Duration,
+    pub red: Duration,
+    pub blinking: Duration,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            yellow: Duration::from_secs(1),
+            red: Duration::from_secs(10),
+            blinking: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Parse the thresholds as seconds for yellow, red and blinking, comma separated, such as 1,10,60.
+pub fn parse_thresholds(text: &str) -> Result<Thresholds, String> {
+    let seconds: Vec<f64> = text
+        .split(',')
+        .map(|s| s.trim().parse::<f64>().ok().filter(|s| *s >= 0.0))
+        .collect::<Option<Vec<f64>>>()
+        .ok_or_else(|| format!("invalid time colors, expected seconds for yellow, red and blinking: {}", text))?;
+    match seconds[..] {
+        [yellow, red, blinking] if yellow <= red && red <= blinking => Ok(Thresholds {
+            yellow: Duration::from_secs_f64(yellow),
+            red: Duration::from_secs_f64(red),
+            blinking: Duration::from_secs_f64(blinking),
+        }),
+        _ => Err(format!("expected three increasing seconds for yellow, red and blinking: {}", text)),
+    }
+}
+
+/// The style of the time of a query, when it is over a threshold.
+pub fn time_style(thresholds: &Thresholds, query_time_ms: u32) -> Option<Style> {
+    let time = Duration::from_millis(query_time_ms as u64);
+    if time > thresholds.blinking {
+        Some(BLINKING_RED)
+    } else if time > thresholds.red {
+        Some(RED)
+    } else if time > thresholds.yellow {
+        Some(YELLOW)
+    } else {
+        None
+    }
+}
+
+/// The style of a status and wait event: YSQL sessions idle in a transaction are magenta, active ones
+/// that wait, such as on Lock:transactionid, yellow and the other active ones green, and YCQL batches are cyan.
+pub fn status_style(api: &str, status: &str, wait: &str) -> Option<Style> {
+    match (api, status) {
+        ("YSQL", status) if status.starts_with("idle in transaction") => Some(MAGENTA),
+        ("YSQL", "active") if !wait.is_empty() => Some(YELLOW),
+        ("YSQL", "active") => Some(GREEN),
+        ("YCQL", "BATCH") => Some(CYAN),
+        _ => None,
+    }
+}
+
+/// The text in a style, or without one, as it is.
+pub fn styled(text: &str, style: Option<Style>) -> String {
+    match style {
+        Some((on, off)) => format!("{}{}{}", on, text, off),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_by_age_and_status() {
+        let thresholds = parse_thresholds("1, 10,60").unwrap();
+        assert_eq!(thresholds, Thresholds::default());
+        assert_eq!(time_style(&thresholds, 500), None);
+        assert_eq!(time_style(&thresholds, 1500), Some(YELLOW));
+        assert_eq!(time_style(&thresholds, 26853), Some(RED));
+        assert_eq!(time_style(&thresholds, 120000), Some(BLINKING_RED));
+        assert!(parse_thresholds("10,1,60").is_err());
+        assert!(parse_thresholds("1,10").is_err());
+        assert!(parse_thresholds("1,ten,60").is_err());
+
+        assert_eq!(status_style("YSQL", "idle in transaction (aborted)", "Client:ClientRead"), Some(MAGENTA));
+        assert_eq!(status_style("YSQL", "active", "Lock:transactionid"), Some(YELLOW));
+        assert_eq!(status_style("YSQL", "active", ""), Some(GREEN));
+        assert_eq!(status_style("YSQL", "idle", "Client:ClientRead"), None);
+        assert_eq!(status_style("YCQL", "BATCH", ""), Some(CYAN));
+        assert_eq!(status_style("YCQL", "QUERY", ""), None);
+        assert_eq!(styled("active", Some(GREEN)), "\x1b[32mactive\x1b[39m");
+    }
+}
@@ -0,0 +1,212 @@
+//! Skew between the servers: smart drivers should spread the sessions over the nodes, a misconfigured
+//! one pins them to one node. Per API the active sessions and the connections are counted per server,
+//! and the skew is the count of the busiest server divided by the mean count of the servers.
+use crate::rpcz::AllConnections::{Connections, InboundConnections};
+use crate::snapshot::{ClusterSnapshot, EndpointSnapshot};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+/// The counts of an API per server in a refresh.
+#[derive(Debug, Clone)]
+pub struct ApiSkew {
+    pub api: String,
+    /// the active sessions per server.
+    pub active: BTreeMap<String, usize>,
+    /// the connections per server.
+    pub connections: BTreeMap<String, usize>,
+    /// the server with the most active sessions, and for how many refreshes in a row it was the busiest
+    /// with an active skew at or over the threshold, and at least as many active sessions as servers.
+    pub busiest: String,
+    pub skewed_refreshes: usize,
+}
+
+impl ApiSkew {
+    pub fn active_skew(&self) -> Option<f64> {
+        skew(&self.active)
+    }
+
+    pub fn connection_skew(&self) -> Option<f64> {
+        skew(&self.connections)
+    }
+}
+
+/// The maximum divided by the mean, when there are two or more servers and a count that isn't zero.
+pub fn skew(counts: &BTreeMap<String, usize>) -> Option<f64> {
+    let total: usize = counts.values().sum();
+    if counts.len() < 2 || total == 0 {
+        return None;
+    }
+    let mean = total as f64 / counts.len() as f64;
+    counts.values().max().map(|max| *max as f64 / mean)
+}
+
+/// The API of the sessions of an endpoint: YSQL for connections, and by the port for inbound
+/// connections, or YCQL when these have CQL details. Tserver endpoints have no API.
+fn endpoint_api(endpoint_snapshot: &EndpointSnapshot) -> Option<&'static str> {
+    match &endpoint_snapshot.connections {
+        Some(Connections { .. }) => Some("YSQL"),
+        Some(InboundConnections { inbound_connections }) => match endpoint_snapshot.endpoint.port.as_str() {
+            "12000" => Some("YCQL"),
+            "11000" => Some("YEDS"),
+            _ if inbound_connections.iter().any(|c| c.connection_details.is_some()) => Some("YCQL"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub struct SkewTracker {
+    threshold: f64,
+    /// per API, the busiest server and the refreshes in a row it was skewed.
+    streaks: HashMap<String, (String, usize)>,
+}
+
+impl SkewTracker {
+    /// Count the refreshes in which the busiest server has an active skew of threshold or more.
+    pub fn new(threshold: f64) -> Self {
+        SkewTracker {
+            threshold,
+            streaks: HashMap::new(),
+        }
+    }
+
+    /// The counts per API of the endpoints that answered.
+    pub fn update(&mut self, snapshot: &ClusterSnapshot) -> Vec<ApiSkew> {
+        let mut apis: BTreeMap<&str, ApiSkew> = BTreeMap::new();
+        for endpoint_snapshot in &snapshot.endpoints {
+            let api = match endpoint_api(endpoint_snapshot) {
+                Some(api) => api,
+                None => continue,
+            };
+            let connections = match &endpoint_snapshot.connections {
+                Some(Connections { connections }) => connections.len(),
+                Some(InboundConnections { inbound_connections }) => inbound_connections.len(),
+                _ => 0,
+            };
+            let server = endpoint_snapshot.endpoint.hostname.clone();
+            let api_skew = apis.entry(api).or_insert_with(|| ApiSkew {
+                api: api.to_string(),
+                active: BTreeMap::new(),
+                connections: BTreeMap::new(),
+                busiest: String::new(),
+                skewed_refreshes: 0,
+            });
+            *api_skew.active.entry(server.clone()).or_default() += endpoint_snapshot.sessions(false).len();
+            *api_skew.connections.entry(server).or_default() += connections;
+        }
+        let mut streaks = HashMap::new();
+        for api_skew in apis.values_mut() {
+            // the first server with the most active sessions.
+            let busiest = api_skew
+                .active
+                .iter()
+                .fold(None, |busiest: Option<(&String, usize)>, (server, count)| match busiest {
+                    Some((_, max)) if max >= *count => busiest,
+                    _ => Some((server, *count)),
+                })
+                .map(|(server, _)| server.clone())
+                .unwrap_or_default();
+            // with fewer active sessions than servers, one session on one server is no skew.
+            let enough_active = api_skew.active.values().sum::<usize>() >= api_skew.active.len();
+            if enough_active && api_skew.active_skew().is_some_and(|skew| skew >= self.threshold) {
+                let refreshes = match self.streaks.get(&api_skew.api) {
+                    Some((server, refreshes)) if *server == busiest => refreshes + 1,
+                    _ => 1,
+                };
+                streaks.insert(api_skew.api.clone(), (busiest.clone(), refreshes));
+                api_skew.skewed_refreshes = refreshes;
+            }
+            api_skew.busiest = busiest;
+        }
+        self.streaks = streaks;
+        apis.into_values().collect()
+    }
+}
+
+/// Write the skew of the APIs with two or more servers on a line, and a warning for every API
+/// of which the busiest server was skewed for more than warn_refreshes refreshes.
+pub fn write_skew(out: &mut dyn Write, skews: &[ApiSkew], warn_refreshes: usize) -> io::Result<()> {
+    let line: Vec<String> = skews
+        .iter()
+        .filter(|s| s.active.len() >= 2)
+        .map(|s| {
+            format!(
+                "{} active {} conn {}",
+                s.api,
+                s.active_skew().map_or(String::from("-"), |skew| format!("{:.1}x", skew)),
+                s.connection_skew().map_or(String::from("-"), |skew| format!("{:.1}x", skew))
+            )
+        })
+        .collect();
+    if !line.is_empty() {
+        writeln!(out, "skew (max/mean per server): {}", line.join("  "))?;
+    }
+    for s in skews.iter().filter(|s| s.skewed_refreshes > warn_refreshes) {
+        writeln!(
+            out,
+            "WARNING: {} has {} of {} active {} sessions for {} refreshes",
+            s.busiest,
+            s.active.get(&s.busiest).copied().unwrap_or_default(),
+            s.active.values().sum::<usize>(),
+            s.api,
+            s.skewed_refreshes
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::fixtures;
+
+    fn snapshot(active_per_server: &[usize]) -> ClusterSnapshot {
+        let rpcz: Vec<(String, String)> = active_per_server
+            .iter()
+            .enumerate()
+            .map(|(n, active)| {
+                let connections: Vec<String> = (0..active + 1)
+                    .map(|i| {
+                        format!(
+                            r#"{{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_status": "{}", "host": "10.0.0.5", "port": "{}"}}"#,
+                            if i < *active { "active" } else { "idle" },
+                            50000 + i
+                        )
+                    })
+                    .collect();
+                (format!("192.168.66.8{}:13000", n), fixtures::ysql(&connections))
+            })
+            .collect();
+        let rpcz: Vec<(&str, &str)> = rpcz.iter().map(|(address, json)| (address.as_str(), json.as_str())).collect();
+        fixtures::snapshot(0, &rpcz)
+    }
+
+    #[test]
+    fn warn_for_pinned_server() {
+        let mut tracker = SkewTracker::new(2.0);
+        let skews = tracker.update(&snapshot(&[1, 1, 1]));
+        assert_eq!(skews.len(), 1);
+        assert_eq!(skews[0].active_skew(), Some(1.0));
+        assert_eq!(skews[0].skewed_refreshes, 0);
+
+        for refreshes in 1..=3 {
+            let skews = tracker.update(&snapshot(&[0, 9, 0]));
+            assert_eq!(skews[0].busiest, "192.168.66.81");
+            assert_eq!(skews[0].active_skew(), Some(3.0));
+            assert_eq!(skews[0].skewed_refreshes, refreshes);
+        }
+        let skews = tracker.update(&snapshot(&[0, 9, 0]));
+        let mut out = Vec::new();
+        write_skew(&mut out, &skews, 3).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "skew (max/mean per server): YSQL active 3.0x conn 2.5x\nWARNING: 192.168.66.81 has 9 of 9 active YSQL sessions for 4 refreshes\n"
+        );
+
+        // a single session is no skew.
+        assert_eq!(tracker.update(&snapshot(&[0, 1, 0]))[0].skewed_refreshes, 0);
+        // another busiest server starts a new count.
+        let skews = tracker.update(&snapshot(&[9, 0, 0]));
+        assert_eq!(skews[0].skewed_refreshes, 1);
+    }
+}
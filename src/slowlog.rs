@@ -0,0 +1,187 @@
+//! Catching the long queries that finish between refreshes: a row that ran longer than a threshold
+//! and is gone, or runs another statement, is kept in a rolling list, and can be written to a log file.
+use crate::filter::{self, Filter};
+use crate::snapshot::GeneralPresentation;
+use crate::syntax;
+use crate::tracking::{RowState, TrackedRow};
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// A long query that finished, as it was last seen running.
+#[derive(Debug, Clone)]
+pub struct SlowQuery {
+    /// the time of the refresh that saw it was gone.
+    pub finished: SystemTime,
+    pub row: GeneralPresentation,
+}
+
+pub struct SlowQueryLog {
+    threshold: Duration,
+    capacity: usize,
+    /// the newest first.
+    entries: VecDeque<SlowQuery>,
+    file: Option<File>,
+}
+
+impl SlowQueryLog {
+    /// Keep the last capacity queries that were seen running for at least threshold.
+    pub fn new(threshold: Duration, capacity: usize) -> Self {
+        SlowQueryLog {
+            threshold,
+            capacity,
+            entries: VecDeque::new(),
+            file: None,
+        }
+    }
+
+    /// Also append every query to a log file.
+    pub fn with_file(mut self, path: &Path) -> io::Result<Self> {
+        self.file = Some(OpenOptions::new().create(true).append(true).open(path)?);
+        Ok(self)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &SlowQuery> {
+        self.entries.iter()
+    }
+
+    /// Add the finished rows of a refresh that ran at least the threshold. The rows of an endpoint that
+    /// couldn't be read are not finished, the tracker keeps them until the endpoint can be read again.
+    pub fn add(&mut self, timestamp: SystemTime, rows: &[TrackedRow]) -> io::Result<()> {
+        for tracked in rows {
+            if tracked.state != RowState::Finished || (tracked.row.query_time_ms as u128) < self.threshold.as_millis() {
+                continue;
+            }
+            let slow_query = SlowQuery {
+                finished: timestamp,
+                row: tracked.row.clone(),
+            };
+            if let Some(file) = &mut self.file {
+                write_slow_query(file, &slow_query)?;
+                file.flush()?;
+            }
+            self.entries.push_front(slow_query);
+            self.entries.truncate(self.capacity);
+        }
+        Ok(())
+    }
+}
+
+fn write_slow_query(out: &mut dyn Write, slow_query: &SlowQuery) -> io::Result<()> {
+    let row = &slow_query.row;
+    writeln!(
+        out,
+        "{} {:4} {:20} {:20} {:10} {:8.3} {}",
+        DateTime::<Local>::from(slow_query.finished).format("%Y-%m-%d %H:%M:%S"),
+        row.api,
+        row.server,
+        row.client,
+        row.keyspace_db_name,
+        row.query_time_ms as f32 / 1000.0,
+        // one line per query in the log.
+        syntax::one_line(&row.query)
+    )
+}
+
+/// Write the recently finished long queries that match the filters, when there are any.
+pub fn write_slow_queries(out: &mut dyn Write, log: &SlowQueryLog, filters: &[Filter]) -> io::Result<()> {
+    let entries: Vec<&SlowQuery> = log.entries.iter().filter(|e| filter::matches_all(filters, &e.row)).collect();
+    if entries.is_empty() {
+        return Ok(());
+    }
+    writeln!(out)?;
+    writeln!(out, "Recently finished queries that ran {}s or longer", log.threshold.as_secs_f32())?;
+    writeln!(
+        out,
+        "{:19} {:4} {:20} {:20} {:10} {:>8} query",
+        "finished", "API", "server", "client", "key/db", "time_s"
+    )?;
+    for slow_query in entries {
+        write_slow_query(out, slow_query)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::{fixtures, ClusterSnapshot};
+    use crate::tracking::SessionTracker;
+    use std::time::UNIX_EPOCH;
+
+    fn snapshot(seconds: u64, rpcz: &str) -> ClusterSnapshot {
+        fixtures::snapshot(seconds, &[("192.168.66.80:13000", rpcz)])
+    }
+
+    fn session(port: &str, query_start_time: &str, ms: u32) -> String {
+        format!(
+            r#"{{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_status": "active", "db_name": "yugabyte", "host": "127.0.0.1", "port": "{}", "query": "select pg_sleep(40)", "query_start_time": "{}", "query_running_for_ms": {}}}"#,
+            port, query_start_time, ms
+        )
+    }
+
+    #[test]
+    fn catch_finished_long_queries() {
+        let file = std::env::temp_dir().join(format!("ybtop_slowlog_{}.log", std::process::id()));
+        let mut log = SlowQueryLog::new(Duration::from_secs(30), 10).with_file(&file).unwrap();
+        let mut tracker = SessionTracker::new();
+        let refreshes = [
+            snapshot(0, &format!(r#"{{"connections": [{}, {}]}}"#, session("50736", "t1", 37000), session("50737", "t1", 1000))),
+            // 50736 runs its next statement, 50737 is gone.
+            snapshot(3, &format!(r#"{{"connections": [{}]}}"#, session("50736", "t2", 100))),
+            // and 50736 is gone, after a short statement.
+            snapshot(6, r#"{"connections": []}"#),
+        ];
+        for snapshot in &refreshes {
+            let rows = tracker.update(snapshot, false);
+            log.add(snapshot.timestamp, &rows).unwrap();
+        }
+        let entries: Vec<&SlowQuery> = log.entries().collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].row.client, "127.0.0.1:50736");
+        assert_eq!(entries[0].row.query_time_ms, 37000);
+        assert_eq!(entries[0].finished, UNIX_EPOCH + Duration::from_secs(3));
+        let logged = std::fs::read_to_string(&file).unwrap();
+        assert_eq!(logged.lines().count(), 1);
+        assert!(logged.ends_with("  37.000 select pg_sleep(40)\n"));
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn catch_query_that_turns_idle() {
+        let mut log = SlowQueryLog::new(Duration::from_secs(30), 10);
+        let mut tracker = SessionTracker::new();
+        let idle = snapshot(
+            3,
+            r#"{"connections": [{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_status": "idle", "host": "127.0.0.1", "port": "50736", "query": "select pg_sleep(40)", "query_start_time": "t1"}]}"#,
+        );
+        for snapshot in &[snapshot(0, &format!(r#"{{"connections": [{}]}}"#, session("50736", "t1", 37000))), idle] {
+            let rows = tracker.update(snapshot, true);
+            log.add(snapshot.timestamp, &rows).unwrap();
+        }
+        let entries: Vec<&SlowQuery> = log.entries().collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].row.query_time_ms, 37000);
+        // one line, with the control characters escaped.
+        let mut slow_query = entries[0].clone();
+        slow_query.row.query = String::from("select\r\n'\x1b[2J'");
+        let mut out = Vec::new();
+        write_slow_query(&mut out, &slow_query).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("  37.000 select '\\u{1b}[2J'\n"));
+    }
+
+    #[test]
+    fn unreadable_endpoint_is_not_finished() {
+        let mut log = SlowQueryLog::new(Duration::from_secs(30), 10);
+        let mut tracker = SessionTracker::new();
+        let running = |seconds| snapshot(seconds, &format!(r#"{{"connections": [{}]}}"#, session("50736", "t1", 37000)));
+        for snapshot in &[running(0), fixtures::timed_out(3, "192.168.66.80:13000"), running(6)] {
+            let rows = tracker.update(snapshot, false);
+            log.add(snapshot.timestamp, &rows).unwrap();
+        }
+        assert_eq!(log.entries().count(), 0);
+    }
+}
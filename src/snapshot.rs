@@ -0,0 +1,782 @@
+//! Collecting the activity of all endpoints of a cluster in one snapshot.
+use crate::activity::{self, Activity};
+use crate::connmgr::{self, ConnectionManagerStats};
+use crate::error::Error;
+use crate::rpcz::AllConnections::{self, Connections, InboundConnections};
+use crate::rpcz::{self, CallsInFlight, Connection, InboundConnection, ServerVersion};
+use crate::source::{HttpSource, Schedule, Source};
+use crate::tablets::{self, Entities, TserverCall};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// A web server of a node: the YSQL, YCQL, YEDIS or tserver web port.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Endpoint {
+    pub hostname: String,
+    pub port: String,
+}
+
+impl Endpoint {
+    pub fn new(hostname: &str, port: &str) -> Self {
+        Endpoint {
+            hostname: hostname.to_string(),
+            port: port.to_string(),
+        }
+    }
+
+    /// An address as host:port.
+    pub fn from_address(address: &str) -> Self {
+        match address.rsplit_once(':') {
+            Some((hostname, port)) => Endpoint::new(hostname, port),
+            None => Endpoint::new(address, ""),
+        }
+    }
+
+    /// Every port on every host.
+    pub fn from_hosts_and_ports(hostname_vec: &[&str], port_vec: &[&str]) -> Vec<Endpoint> {
+        let mut endpoints = Vec::new();
+        for hostname in hostname_vec {
+            for port in port_vec {
+                endpoints.push(Endpoint::new(hostname, port));
+            }
+        }
+        endpoints
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.hostname, self.port)
+    }
+}
+
+/// What one endpoint returned. When it couldn't be read, connections is None and error tells why.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EndpointSnapshot {
+    pub endpoint: Endpoint,
+    pub version: Option<String>,
+    pub connections: Option<AllConnections>,
+    pub error: Option<String>,
+    /// the fields ybtop needs that the endpoint's /rpcz didn't have.
+    pub missing_fields: BTreeSet<String>,
+    pub connection_manager: Option<ConnectionManagerStats>,
+    /// the backends in pg_stat_activity, when it is read, added to the YSQL sessions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub activity: Vec<Activity>,
+}
+
+impl EndpointSnapshot {
+    /// The snapshot of an endpoint from the result of reading its /rpcz, without version or connection manager stats.
+    pub fn from_rpcz(endpoint: Endpoint, get_result: Result<String, Error>) -> Self {
+        EndpointSnapshot::from_rpcz_of_version(endpoint, None, get_result)
+    }
+
+    /// The snapshot of an endpoint of a version from the result of reading its /rpcz, without connection manager stats.
+    pub fn from_rpcz_of_version(endpoint: Endpoint, version: Option<String>, get_result: Result<String, Error>) -> Self {
+        let (connections, error, missing_fields) = match parse_rpcz(get_result, version.as_deref()) {
+            Ok((connections, missing_fields)) => (Some(connections), None, missing_fields),
+            Err(error) => (None, Some(error.to_string()), BTreeSet::new()),
+        };
+        EndpointSnapshot {
+            endpoint,
+            version,
+            connections,
+            error,
+            missing_fields,
+            connection_manager: None,
+            activity: Vec::new(),
+        }
+    }
+
+    /// The sessions of this endpoint, in the order of its /rpcz output.
+    /// YSQL sessions with an empty status, the background processes, or idle, are left out unless idle
+    /// is set. A client backend without the status field is shown.
+    pub fn sessions(&self, idle: bool) -> Vec<GeneralPresentation> {
+        self.rows(idle).into_iter().map(|(row, _)| row).collect()
+    }
+
+    /// Every field the endpoint's /rpcz has of the session of a row, as json: the YSQL connection,
+    /// or the connection and its call in flight, in call_in_flight.
+    pub fn raw_fields(&self, row: &GeneralPresentation) -> Option<Value> {
+        self.raw(row).and_then(|raw| match raw {
+            Raw::Connection(connection) => serde_json::to_value(connection).ok(),
+            Raw::Call(connection, cif) => {
+                let mut value = serde_json::to_value(connection).ok()?;
+                let fields = value.as_object_mut()?;
+                fields.remove("calls_in_flight");
+                fields.insert(String::from("call_in_flight"), serde_json::to_value(cif).ok()?);
+                Some(value)
+            }
+        })
+    }
+
+    /// The fields of the session of a row that ybtop doesn't know, with their values, as json: those of
+    /// the YSQL connection, or those of the connection and, in call_in_flight, of its call in flight.
+    pub fn extra_fields(&self, row: &GeneralPresentation) -> Option<Value> {
+        self.raw(row).map(|raw| {
+            let (connection, call) = match raw {
+                Raw::Connection(connection) => (&connection.extra, None),
+                Raw::Call(connection, cif) => (&connection.extra, Some(&cif.extra)),
+            };
+            let mut fields: serde_json::Map<String, Value> = connection.clone().into_iter().collect();
+            if let Some(call) = call.filter(|call| !call.is_empty()) {
+                fields.insert(String::from("call_in_flight"), Value::Object(call.clone().into_iter().collect()));
+            }
+            Value::Object(fields)
+        })
+    }
+
+    fn raw(&self, row: &GeneralPresentation) -> Option<Raw<'_>> {
+        self.rows(true)
+            .into_iter()
+            .find(|(r, _)| r.session == row.session && r.call == row.call)
+            .map(|(_, raw)| raw)
+    }
+
+    /// The sessions with what they were made of.
+    fn rows(&self, idle: bool) -> Vec<(GeneralPresentation, Raw<'_>)> {
+        let mut database_activity = Vec::new();
+        let hostname = &self.endpoint.hostname;
+        match &self.connections {
+            Some(Connections { connections }) => {
+                let backends: HashMap<(&str, i64), &Activity> =
+                    self.activity.iter().map(|a| ((a.client.as_str(), a.backend_start), a)).collect();
+                for connection in connections {
+                    let shown = match connection.backend_status.as_deref() {
+                        Some(status) => !status.is_empty() && status != "idle",
+                        // the status was renamed to a name ybtop doesn't know: the client backends are shown.
+                        None => connection.backend_type == "client backend",
+                    };
+                    if idle || shown {
+                        let client = format!(
+                            "{}:{}",
+                            connection.host.clone().unwrap_or_default(),
+                            connection.port.clone().unwrap_or_default()
+                        );
+                        let activity = activity::micros(&connection.process_start_time)
+                            .and_then(|start| backends.get(&(client.as_str(), start)))
+                            .map(|a| (*a).clone());
+                        let wait = match (&activity, &connection.wait_event_type, &connection.wait_event) {
+                            (Some(activity), _, _) => Some(activity.wait()),
+                            (None, None, None) => None,
+                            (None, event_type, event) => Some(activity::wait_event(
+                                event_type.as_deref().unwrap_or_default(),
+                                event.as_deref().unwrap_or_default(),
+                            )),
+                        };
+                        let row = GeneralPresentation {
+                            api: String::from("YSQL"),
+                            server: hostname.to_string(),
+                            client,
+                            keyspace_db_name: connection.db_name.clone().unwrap_or_default(),
+                            application: connection.application_name.clone(),
+                            status: connection.backend_status.clone().unwrap_or_default(),
+                            query_time_ms: connection.query_running_for_ms.unwrap_or_default(),
+                            query: connection.query.clone().unwrap_or_default(),
+                            statements: Vec::new(),
+                            session: format!(
+                                "{} {}:{} {}",
+                                hostname,
+                                connection.host.clone().unwrap_or_default(),
+                                connection.port.clone().unwrap_or_default(),
+                                connection.process_start_time
+                            ),
+                            call: connection
+                                .query_start_time
+                                .clone()
+                                .unwrap_or_else(|| connection.query.clone().unwrap_or_default()),
+                            activity,
+                            wait,
+                        };
+                        database_activity.push((row, Raw::Connection(connection)));
+                    }
+                }
+            }
+            Some(InboundConnections { inbound_connections }) => {
+                for connection in inbound_connections {
+                    for cif in connection.calls_in_flight.iter().flatten() {
+                        if let Some(redis_details) = &cif.redis_details {
+                            let (command, key, redis_string) = match &redis_details.call_details[..] {
+                                // a call that doesn't tell its command.
+                                [] => (String::from("UNKNOWN"), String::new(), String::new()),
+                                [call_details] => {
+                                    let (command, key) = rpcz::redis_command_and_key(&call_details.redis_string);
+                                    (command, key, call_details.redis_string.clone())
+                                }
+                                calls => (
+                                    String::from("PIPELINE"),
+                                    String::from(""),
+                                    format!("Number of commands: {}", calls.len()),
+                                ),
+                            };
+                            let row = GeneralPresentation {
+                                api: String::from("YEDS"),
+                                server: hostname.to_string(),
+                                client: connection.remote_ip.clone(),
+                                keyspace_db_name: key,
+                                application: String::new(),
+                                status: command,
+                                query_time_ms: cif.elapsed_millis,
+                                session: format!("{} {}", hostname, connection.remote_ip),
+                                call: call_identity(cif, &redis_string),
+                                query: redis_string,
+                                statements: if redis_details.call_details.len() > 1 {
+                                    redis_details.call_details.iter().map(|c| c.redis_string.clone()).collect()
+                                } else {
+                                    Vec::new()
+                                },
+                                activity: None,
+                                wait: None,
+                            };
+                            database_activity.push((row, Raw::Call(connection, cif)));
+                            continue;
+                        }
+                        // tserver calls have a request header instead of cql details
+                        let cql_details = match &cif.cql_details {
+                            Some(cql_details) => cql_details,
+                            None => continue,
+                        };
+                        let keyspace_name = match connection.connection_details.as_ref() {
+                            Some(details) => details.cql_connection_details.keyspace.clone(),
+                            None => String::from(""),
+                        };
+                        let sql_string = if cql_details.call_details.len() == 1 {
+                            cql_details.call_details[0].sql_string.to_string()
+                        } else {
+                            format!("Number of statements: {}", cql_details.call_details.len())
+                        };
+                        let row = GeneralPresentation {
+                            api: String::from("YCQL"),
+                            server: hostname.to_string(),
+                            client: connection.remote_ip.clone(),
+                            keyspace_db_name: keyspace_name,
+                            application: String::new(),
+                            status: cql_details.call_type.clone(),
+                            query_time_ms: cif.elapsed_millis,
+                            session: format!("{} {}", hostname, connection.remote_ip),
+                            call: call_identity(cif, &sql_string),
+                            query: sql_string,
+                            statements: if cql_details.call_details.len() == 1 {
+                                Vec::new()
+                            } else {
+                                cql_details.call_details.iter().map(|c| c.sql_string.clone()).collect()
+                            },
+                            activity: None,
+                            wait: None,
+                        };
+                        database_activity.push((row, Raw::Call(connection, cif)));
+                    }
+                }
+            }
+            _ => {}
+        }
+        database_activity
+    }
+
+    /// The fields the endpoint's /rpcz had that ybtop doesn't know.
+    pub fn unknown_fields(&self) -> BTreeSet<String> {
+        self.connections.as_ref().map(rpcz::unknown_fields).unwrap_or_default()
+    }
+}
+
+/// What a row of the session list was made of.
+enum Raw<'a> {
+    Connection(&'a Connection),
+    Call(&'a InboundConnection, &'a CallsInFlight),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClusterSnapshot {
+    pub timestamp: SystemTime,
+    pub endpoints: Vec<EndpointSnapshot>,
+    /// the master's entities, when masters were given and one of them answered.
+    pub entities: Option<Entities>,
+}
+
+/// A YSQL session or a YCQL or YEDIS call in flight, as a row of the session list.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GeneralPresentation {
+    pub api: String,
+    pub server: String,
+    pub client: String,
+    pub keyspace_db_name: String,
+    /// the YSQL application_name.
+    #[serde(default)]
+    pub application: String,
+    pub status: String,
+    pub query_time_ms: u32,
+    pub query: String,
+    /// the statements of a YCQL batch or the commands of a YEDIS pipeline, of which query only tells the number.
+    #[serde(default)]
+    pub statements: Vec<String>,
+    /// stays the same over refreshes for the same session: the server, client and backend
+    /// start time for YSQL, the server and client for YCQL and YEDIS.
+    #[serde(default)]
+    pub session: String,
+    /// tells the statements of a session apart: the query start time for YSQL,
+    /// the call id, or without it the statement, for YCQL and YEDIS.
+    #[serde(default)]
+    pub call: String,
+    /// what pg_stat_activity tells of a YSQL session, when it is read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity: Option<Activity>,
+    /// the wait event with its type of a YSQL session, empty when it doesn't wait, when pg_stat_activity
+    /// or the /rpcz tells.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait: Option<String>,
+}
+
+impl ClusterSnapshot {
+    /// The time the sessions of this snapshot are taken to have run since a previous snapshot: the time
+    /// in between, at most the refresh interval, so a pause or a stalled refresh doesn't count as running.
+    pub fn sampled_since(&self, previous: SystemTime, refresh: Duration) -> Option<Duration> {
+        self.timestamp
+            .duration_since(previous)
+            .ok()
+            .filter(|elapsed| !elapsed.is_zero())
+            .map(|elapsed| elapsed.min(refresh))
+    }
+
+    /// The sessions of all endpoints, longest running first.
+    /// YSQL sessions with an empty status, the background processes, or idle, are left out unless idle
+    /// is set. A client backend without the status field is shown.
+    pub fn sessions(&self, idle: bool) -> Vec<GeneralPresentation> {
+        let mut database_activity: Vec<GeneralPresentation> =
+            self.endpoints.iter().flat_map(|e| e.sessions(idle)).collect();
+        database_activity.sort_by_key(|d| d.query_time_ms);
+        database_activity.reverse();
+        database_activity
+    }
+
+    /// Every field of the session of a row, as read from its endpoint.
+    pub fn raw_fields(&self, row: &GeneralPresentation) -> Option<Value> {
+        self.endpoints.iter().find_map(|e| e.raw_fields(row))
+    }
+
+    /// The fields of the session of a row that ybtop doesn't know, as read from its endpoint.
+    pub fn extra_fields(&self, row: &GeneralPresentation) -> Option<Value> {
+        self.endpoints.iter().find_map(|e| e.extra_fields(row))
+    }
+
+    /// The tserver calls in flight of all endpoints.
+    pub fn tserver_calls(&self) -> Vec<TserverCall> {
+        let mut tserver_calls = Vec::new();
+        for endpoint_snapshot in &self.endpoints {
+            if let Some(InboundConnections { inbound_connections }) = &endpoint_snapshot.connections {
+                for connection in inbound_connections {
+                    for cif in connection.calls_in_flight.iter().flatten() {
+                        if cif.cql_details.is_none() && cif.redis_details.is_none() {
+                            tserver_calls.push(TserverCall {
+                                elapsed_ms: cif.elapsed_millis,
+                                tablet_id: cif.trace_buffer.as_deref().and_then(tablets::tablet_id_from_trace),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        tserver_calls
+    }
+
+    /// The connection manager stats per server, for the endpoints that have a connection manager.
+    pub fn connection_manager_stats(&self) -> Vec<(&str, &ConnectionManagerStats)> {
+        self.endpoints
+            .iter()
+            .filter_map(|e| e.connection_manager.as_ref().map(|stats| (e.endpoint.hostname.as_str(), stats)))
+            .collect()
+    }
+}
+
+/// What a collector knows of the version of an endpoint.
+#[derive(Default)]
+struct VersionRead {
+    version: Option<String>,
+    /// the reads that failed in a row.
+    failures: u32,
+    /// the refresh from which the version is read again.
+    retry_at: u64,
+}
+
+/// Collects snapshots of a set of endpoints from a source. The version of an endpoint is read once;
+/// when it couldn't be read, it is read again after 1, 2, 4 and up to 64 refreshes.
+pub struct Collector {
+    pub endpoints: Vec<Endpoint>,
+    /// master web addresses (host:port) to read the entities from, none to not read them.
+    pub masters: Vec<String>,
+    /// read the YSQL connection manager stats of the YSQL endpoints.
+    pub connection_manager: bool,
+    source: Box<dyn Source>,
+    versions: HashMap<Endpoint, VersionRead>,
+    refreshes: u64,
+}
+
+impl Collector {
+    /// A collector that reads the endpoints over http.
+    pub fn new(endpoints: Vec<Endpoint>) -> Self {
+        Collector::with_source(endpoints, Box::new(HttpSource::default()))
+    }
+
+    pub fn with_source(endpoints: Vec<Endpoint>, source: Box<dyn Source>) -> Self {
+        Collector {
+            endpoints,
+            masters: Vec::new(),
+            connection_manager: false,
+            source,
+            versions: HashMap::new(),
+            refreshes: 0,
+        }
+    }
+
+    pub fn schedule(&self) -> Schedule {
+        self.source.schedule()
+    }
+
+    /// The refresh of a replay and the number of refreshes, or None when the source is live.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.source.position()
+    }
+
+    pub fn collect(&mut self) -> Result<ClusterSnapshot, Error> {
+        if self.endpoints.is_empty() {
+            return Err(Error::NoEndpoints);
+        }
+        let timestamp = self.source.begin_refresh();
+        self.refreshes += 1;
+        let mut endpoints = Vec::new();
+        for endpoint in &self.endpoints {
+            // the version first, to read the /rpcz as its release writes it.
+            let read = self.versions.entry(endpoint.clone()).or_default();
+            if read.version.is_none() && self.refreshes >= read.retry_at {
+                read.version = read_version(self.source.as_mut(), endpoint);
+                if read.version.is_none() {
+                    read.failures += 1;
+                    read.retry_at = self.refreshes + (1 << (read.failures - 1).min(6));
+                }
+            }
+            let mut endpoint_snapshot =
+                EndpointSnapshot::from_rpcz_of_version(endpoint.clone(), read.version.clone(), self.source.fetch(endpoint, "/rpcz"));
+            if self.connection_manager && matches!(endpoint_snapshot.connections, Some(Connections { .. })) {
+                endpoint_snapshot.connection_manager = self
+                    .source
+                    .fetch(endpoint, "/connections")
+                    .ok()
+                    .and_then(connmgr::parse_connection_manager);
+            }
+            endpoints.push(endpoint_snapshot);
+        }
+        // any master can serve the entities, so take the first one that answers.
+        let entities = self.masters.iter().find_map(|master| {
+            self.source
+                .fetch(&Endpoint::from_address(master), "/dump-entities")
+                .ok()
+                .and_then(tablets::parse_entities)
+        });
+        Ok(ClusterSnapshot {
+            timestamp,
+            endpoints,
+            entities,
+        })
+    }
+}
+
+/// Collect one snapshot of the endpoints, without entities or connection manager stats.
+pub fn collect_snapshot(endpoints: &[Endpoint]) -> Result<ClusterSnapshot, Error> {
+    Collector::new(endpoints.to_vec()).collect()
+}
+
+/// The default tserver web port. The YSQL web server doesn't serve the version, so it is read from
+/// this port of the same host instead.
+const TSERVER_WEB_PORT: &str = "9000";
+
+/// The version of the node of an endpoint, shown in its line of the endpoints. It is only shown:
+/// the same parser reads the /rpcz of every version.
+fn read_version(source: &mut dyn Source, endpoint: &Endpoint) -> Option<String> {
+    for version_endpoint in [endpoint.clone(), Endpoint::new(&endpoint.hostname, TSERVER_WEB_PORT)] {
+        let get_result = match source.fetch(&version_endpoint, "/api/v1/version") {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        if let Ok(version) = serde_json::from_str::<ServerVersion>(&get_result) {
+            return Some(format!("{}-b{}", version.version_number, version.build_number));
+        }
+    }
+    None
+}
+
+/// The call id of a call in flight, or without it, its statement.
+fn call_identity(cif: &CallsInFlight, statement: &str) -> String {
+    match cif.header.as_ref().and_then(|h| h.call_id) {
+        Some(call_id) => call_id.to_string(),
+        None => statement.to_string(),
+    }
+}
+
+fn parse_rpcz(get_result: Result<String, Error>, version: Option<&str>) -> Result<(AllConnections, BTreeSet<String>), Error> {
+    Ok(rpcz::parse_result_of_version(&get_result?, version)?)
+}
+
+/// Snapshots for the tests of the trackers and views, made of /rpcz pages as the endpoints return them.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::{ClusterSnapshot, Endpoint, EndpointSnapshot};
+    use crate::error::Error;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    /// A snapshot taken seconds after the epoch, of endpoints as host:port with the /rpcz they returned.
+    pub fn snapshot(seconds: u64, rpcz: &[(&str, &str)]) -> ClusterSnapshot {
+        ClusterSnapshot {
+            timestamp: UNIX_EPOCH + Duration::from_secs(seconds),
+            endpoints: rpcz
+                .iter()
+                .map(|(address, http_result)| {
+                    EndpointSnapshot::from_rpcz(Endpoint::from_address(address), Ok(http_result.to_string()))
+                })
+                .collect(),
+            entities: None,
+        }
+    }
+
+    /// A snapshot taken seconds after the epoch, of an endpoint that timed out.
+    pub fn timed_out(seconds: u64, address: &str) -> ClusterSnapshot {
+        ClusterSnapshot {
+            timestamp: UNIX_EPOCH + Duration::from_secs(seconds),
+            endpoints: vec![EndpointSnapshot::from_rpcz(
+                Endpoint::from_address(address),
+                Err(Error::Timeout(address.to_string())),
+            )],
+            entities: None,
+        }
+    }
+
+    /// The /rpcz of a YSQL endpoint with these connections, as json objects.
+    pub fn ysql(connections: &[String]) -> String {
+        format!(r#"{{"connections": [{}]}}"#, connections.join(","))
+    }
+
+    /// The /rpcz of a YCQL endpoint with these inbound connections, as json objects.
+    pub fn ycql(connections: &[String]) -> String {
+        format!(r#"{{"inbound_connections": [{}]}}"#, connections.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::ScriptedSource;
+
+    fn endpoint_snapshot(hostname: &str, port: &str, http_result: &str) -> EndpointSnapshot {
+        EndpointSnapshot::from_rpcz(Endpoint::new(hostname, port), Ok(http_result.to_string()))
+    }
+
+    const YSQL: &str = r#"
+{
+    "connections": [
+        {
+            "db_name": "yugabyte",
+            "query": "select pg_sleep(120);",
+            "process_start_time": "2022-03-27 15:55:22.587029+00",
+            "query_running_for_ms": 7466,
+            "application_name": "ysqlsh",
+            "backend_type": "client backend",
+            "backend_status": "active",
+            "host": "127.0.0.1",
+            "port": "50736"
+        },
+        {
+            "db_name": "yugabyte",
+            "query": "select 1;",
+            "process_start_time": "2022-03-27 15:55:22.587029+00",
+            "application_name": "ysqlsh",
+            "backend_type": "client backend",
+            "backend_status": "idle",
+            "host": "127.0.0.1",
+            "port": "50740"
+        }
+    ]
+}
+    "#;
+
+    const YCQL: &str = r#"
+{
+    "inbound_connections": [
+        {
+            "remote_ip": "127.0.0.1:35518",
+            "state": "OPEN",
+            "processed_call_count": 20,
+            "connection_details": {
+                "cql_connection_details": {
+                    "keyspace": "cr"
+                }
+            },
+            "calls_in_flight": [
+                {
+                    "elapsed_millis": 9100,
+                    "cql_details": {
+                        "type": "QUERY",
+                        "call_details": [
+                            {
+                                "sql_string": "select * from fa_bg_checks;"
+                            }
+                        ]
+                    }
+                }
+            ]
+        }
+    ]
+}
+    "#;
+
+    #[test]
+    fn sessions_sorted_longest_first() {
+        let snapshot = fixtures::snapshot(0, &[("192.168.66.80:13000", YSQL), ("192.168.66.81:12000", YCQL)]);
+        let sessions = snapshot.sessions(false);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].api, "YCQL");
+        assert_eq!(sessions[0].server, "192.168.66.81");
+        assert_eq!(sessions[0].keyspace_db_name, "cr");
+        assert_eq!(sessions[1].api, "YSQL");
+        assert_eq!(sessions[1].client, "127.0.0.1:50736");
+        assert_eq!(snapshot.sessions(true).len(), 3);
+        assert!(snapshot.tserver_calls().is_empty());
+    }
+
+    #[test]
+    fn sessions_with_pg_stat_activity() {
+        let mut ysql = endpoint_snapshot("192.168.66.80", "13000", YSQL);
+        let backend = |client: &str, backend_start: i64| Activity {
+            client: client.to_string(),
+            backend_start,
+            pid: 4242,
+            user: String::from("yugabyte"),
+            wait_event_type: String::from("Timeout"),
+            wait_event: String::from("PgSleep"),
+            query_id: Some(-7),
+        };
+        // the same client with another backend start is another session.
+        ysql.activity = vec![backend("127.0.0.1:50736", 1648396522587029), backend("127.0.0.1:50740", 1648396522000000)];
+        let sessions = ysql.sessions(true);
+        assert_eq!(sessions[0].activity.as_ref().map(|a| a.wait()).as_deref(), Some("Timeout:PgSleep"));
+        assert_eq!(sessions[1].activity, None);
+    }
+
+    #[test]
+    fn fields_of_a_newer_version() {
+        let ysql = endpoint_snapshot(
+            "192.168.66.80",
+            "13000",
+            r#"{"connections": [{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_status": "active", "backend_xid": 742, "host": "10.0.0.5", "port": "50736", "query": "select 1"}]}"#,
+        );
+        let row = &ysql.sessions(false)[0];
+        assert_eq!(ysql.extra_fields(row), Some(serde_json::json!({"backend_xid": 742})));
+        assert_eq!(ysql.raw_fields(row).unwrap()["backend_xid"], 742);
+    }
+
+    #[test]
+    fn renamed_status() {
+        let rpcz = r#"{"connections": [{"process_start_time": "2022-03-27 15:55:22.587029+00", "application_name": "ysqlsh", "backend_type": "client backend", "backend_state": "active", "host": "10.0.0.5", "port": "50736", "query": "select 1"},
+            {"process_start_time": "2022-03-27 15:54:29.555678+00", "application_name": "", "backend_type": "checkpointer", "backend_state": ""}]}"#;
+        let endpoint = Endpoint::new("192.168.66.80", "13000");
+        // read under the name ybtop knows from the version that renamed it.
+        let newer = EndpointSnapshot::from_rpcz_of_version(endpoint.clone(), Some(String::from("2.25.0.0-b1")), Ok(rpcz.to_string()));
+        assert_eq!(newer.sessions(false)[0].status, "active");
+        assert!(newer.missing_fields.is_empty());
+        // without the rename, the client backend is still shown, with an empty status.
+        let older = EndpointSnapshot::from_rpcz_of_version(endpoint, Some(String::from("2.13.0.0-b42")), Ok(rpcz.to_string()));
+        let sessions = older.sessions(false);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].status, "");
+        assert!(older.missing_fields.contains("backend_status"));
+    }
+
+    #[test]
+    fn yedis_call_without_commands() {
+        let yedis = endpoint_snapshot(
+            "192.168.66.80",
+            "11000",
+            r#"{"inbound_connections": [{"remote_ip": "10.0.0.9:41234", "state": "OPEN", "processed_call_count": 3, "calls_in_flight": [{"elapsed_millis": 40, "redis_details": {"call_details": []}}]}]}"#,
+        );
+        let sessions = yedis.sessions(false);
+        assert_eq!(sessions[0].api, "YEDS");
+        assert_eq!(sessions[0].status, "UNKNOWN");
+        assert_eq!(sessions[0].query, "");
+        assert!(sessions[0].statements.is_empty());
+    }
+
+    #[test]
+    fn collect_without_endpoints() {
+        assert!(matches!(collect_snapshot(&[]), Err(Error::NoEndpoints)));
+    }
+
+    #[test]
+    fn collect_flapping_node() {
+        let ysql = Endpoint::new("192.168.66.80", "13000");
+        let mut source = ScriptedSource::new();
+        source.push(&ysql, "/rpcz", Ok(YSQL.to_string()));
+        source.push(&ysql, "/rpcz", Err(Error::NotReachable(ysql.to_string())));
+        source.push(&ysql, "/rpcz", Ok(YSQL.to_string()));
+        source.push(&ysql, "/api/v1/version", Err(Error::NotReachable(ysql.to_string())));
+        source.push(&Endpoint::new("192.168.66.80", TSERVER_WEB_PORT), "/api/v1/version", Ok(String::from(r#"{"version_number": "2.13.0.0", "build_number": "42"}"#)));
+        let mut collector = Collector::with_source(vec![ysql], Box::new(source));
+        let first = collector.collect().unwrap();
+        assert_eq!(first.sessions(false).len(), 1);
+        assert_eq!(first.endpoints[0].version.as_deref(), Some("2.13.0.0-b42"));
+        let second = collector.collect().unwrap();
+        assert!(second.sessions(false).is_empty());
+        assert_eq!(second.endpoints[0].error.as_deref(), Some("192.168.66.80:13000 not reachable"));
+        // the version is remembered, it isn't read again.
+        assert_eq!(second.endpoints[0].version.as_deref(), Some("2.13.0.0-b42"));
+        let third = collector.collect().unwrap();
+        assert_eq!(third.sessions(false).len(), 1);
+    }
+
+    #[test]
+    fn read_version_with_backoff() {
+        let ysql = Endpoint::new("192.168.66.80", "13000");
+        let mut source = ScriptedSource::new();
+        for _ in 0..4 {
+            source.push(&ysql, "/rpcz", Ok(YSQL.to_string()));
+        }
+        source.push(&ysql, "/api/v1/version", Err(Error::Timeout(ysql.to_string())));
+        source.push(&ysql, "/api/v1/version", Err(Error::Timeout(ysql.to_string())));
+        source.push(&ysql, "/api/v1/version", Ok(String::from(r#"{"version_number": "2.13.0.0", "build_number": "42"}"#)));
+        let mut collector = Collector::with_source(vec![ysql], Box::new(source));
+        // read at the first and second refresh, then not until the fourth.
+        let versions: Vec<Option<String>> =
+            (0..4).map(|_| collector.collect().unwrap().endpoints[0].version.clone()).collect();
+        assert_eq!(versions, vec![None, None, None, Some(String::from("2.13.0.0-b42"))]);
+    }
+
+    #[test]
+    fn collect_node_timing_out() {
+        let ysql = Endpoint::new("192.168.66.80", "13000");
+        let ycql = Endpoint::new("192.168.66.81", "12000");
+        let mut source = ScriptedSource::new();
+        source.push(&ysql, "/rpcz", Ok(YSQL.to_string()));
+        source.push(&ycql, "/rpcz", Err(Error::Timeout(ycql.to_string())));
+        let mut collector = Collector::with_source(vec![ysql, ycql], Box::new(source));
+        let snapshot = collector.collect().unwrap();
+        assert_eq!(snapshot.sessions(false).len(), 1);
+        assert_eq!(snapshot.endpoints[1].error.as_deref(), Some("192.168.66.81:12000 timed out"));
+    }
+
+    #[test]
+    fn collect_node_with_10000_connections() {
+        let ysql = Endpoint::new("192.168.66.80", "13000");
+        let connections: Vec<String> = (0..10000)
+            .map(|i| format!(
+                r#"{{"process_start_time": "2022-03-27 15:55:22.587029+00", "application_name": "app", "backend_type": "client backend", "backend_status": "{}", "db_name": "yugabyte", "host": "10.0.0.1", "port": "{}", "query": "select {}", "query_running_for_ms": {}}}"#,
+                if i % 2 == 0 { "active" } else { "idle" }, 20000 + i, i, i
+            ))
+            .collect();
+        let mut source = ScriptedSource::new();
+        source.push(&ysql, "/rpcz", Ok(format!(r#"{{"connections": [{}]}}"#, connections.join(","))));
+        let mut collector = Collector::with_source(vec![ysql], Box::new(source));
+        let sessions = collector.collect().unwrap().sessions(false);
+        assert_eq!(sessions.len(), 5000);
+        assert_eq!(sessions[0].query_time_ms, 9998);
+        assert!(sessions.windows(2).all(|w| w[0].query_time_ms >= w[1].query_time_ms));
+    }
+}
@@ -0,0 +1,209 @@
+//! Where the output of an endpoint's pages comes from: the network, files, or a script for tests.
+use crate::error::Error;
+use crate::snapshot::Endpoint;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// When the next refresh of a source is.
+pub enum Schedule {
+    /// a live source can be refreshed any time.
+    Live,
+    /// a replayed source has its next refresh at the time it was recorded.
+    Next(SystemTime),
+    /// a replayed source has no refreshes left.
+    Finished,
+}
+
+pub trait Source {
+    /// Fetch the output of a page of an endpoint, such as /rpcz.
+    fn fetch(&mut self, endpoint: &Endpoint, path: &str) -> Result<String, Error>;
+
+    /// Called at the start of every refresh, returns the time of the refresh.
+    fn begin_refresh(&mut self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::Live
+    }
+
+    /// The number of the refresh of a replay, counting from 1, and the number of refreshes.
+    fn position(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+/// Reads the pages over http.
+pub struct HttpSource {
+    client: reqwest::blocking::Client,
+}
+
+impl HttpSource {
+    /// A source of which a request, also connecting, takes at most timeout.
+    pub fn new(timeout: Duration) -> Self {
+        HttpSource {
+            client: reqwest::blocking::Client::builder()
+                .connect_timeout(timeout)
+                .timeout(timeout)
+                .build()
+                .unwrap(),
+        }
+    }
+}
+
+impl Default for HttpSource {
+    /// A node that doesn't answer within 5 seconds, also when connecting, is reported as timed out,
+    /// instead of holding up the refresh.
+    fn default() -> Self {
+        HttpSource::new(Duration::from_secs(5))
+    }
+}
+
+impl Source for HttpSource {
+    fn fetch(&mut self, endpoint: &Endpoint, path: &str) -> Result<String, Error> {
+        self.client
+            .get(format!("http://{}{}", endpoint, path))
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text())
+            .map_err(|e| {
+                // a node that drops the connection attempts times out connecting.
+                if e.is_timeout() {
+                    Error::Timeout(endpoint.to_string())
+                } else if e.is_connect() {
+                    Error::NotReachable(endpoint.to_string())
+                } else {
+                    Error::Http(e)
+                }
+            })
+    }
+}
+
+/// Reads the pages from files in a directory, named <hostname>_<port><path>.json with the
+/// slashes in the path replaced by underscores, such as 192.168.66.80_13000_rpcz.json.
+/// A page that couldn't be read has a file named .error instead, with the error.
+pub struct FileSource {
+    directory: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        FileSource {
+            directory: directory.into(),
+        }
+    }
+
+    pub fn file_name(endpoint: &Endpoint, path: &str) -> String {
+        format!("{}_{}{}.json", endpoint.hostname, endpoint.port, path.replace('/', "_"))
+    }
+
+    pub fn error_file_name(endpoint: &Endpoint, path: &str) -> String {
+        format!("{}_{}{}.error", endpoint.hostname, endpoint.port, path.replace('/', "_"))
+    }
+
+    /// The endpoints that have a file of a page, such as /rpcz.
+    pub fn endpoints(&self, path: &str) -> io::Result<Vec<Endpoint>> {
+        let suffix = path.replace('/', "_");
+        let mut endpoints = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            let page = name.strip_suffix(".json").or_else(|| name.strip_suffix(".error"));
+            if let Some((hostname, port)) = page.and_then(|page| page.strip_suffix(&suffix)).and_then(|e| e.rsplit_once('_')) {
+                endpoints.push(Endpoint::new(hostname, port));
+            }
+        }
+        Ok(endpoints)
+    }
+
+    /// The text of an error file: the kind of error, and for other errors, the message.
+    pub fn error_text(error: &Error) -> String {
+        match error {
+            Error::NotReachable(_) => String::from("not reachable"),
+            Error::Timeout(_) => String::from("timed out"),
+            other => format!("other\n{}", other),
+        }
+    }
+}
+
+impl Source for FileSource {
+    fn fetch(&mut self, endpoint: &Endpoint, path: &str) -> Result<String, Error> {
+        if let Ok(text) = fs::read_to_string(self.directory.join(FileSource::error_file_name(endpoint, path))) {
+            return Err(match text.split_once('\n') {
+                Some(("other", message)) => Error::Recorded(message.to_string()),
+                _ if text == "timed out" => Error::Timeout(endpoint.to_string()),
+                _ => Error::NotReachable(endpoint.to_string()),
+            });
+        }
+        fs::read_to_string(self.directory.join(FileSource::file_name(endpoint, path)))
+            .map_err(|_| Error::NotReachable(endpoint.to_string()))
+    }
+}
+
+/// Returns the responses it is given for a page of an endpoint in order, one per fetch.
+/// When they are used up, or when it has none, the endpoint is not reachable.
+#[derive(Default)]
+pub struct ScriptedSource {
+    responses: HashMap<(Endpoint, String), VecDeque<Result<String, Error>>>,
+}
+
+impl ScriptedSource {
+    pub fn new() -> Self {
+        ScriptedSource::default()
+    }
+
+    pub fn push(&mut self, endpoint: &Endpoint, path: &str, response: Result<String, Error>) {
+        self.responses
+            .entry((endpoint.clone(), path.to_string()))
+            .or_default()
+            .push_back(response);
+    }
+}
+
+impl Source for ScriptedSource {
+    fn fetch(&mut self, endpoint: &Endpoint, path: &str) -> Result<String, Error> {
+        self.responses
+            .get_mut(&(endpoint.clone(), path.to_string()))
+            .and_then(|responses| responses.pop_front())
+            .unwrap_or_else(|| Err(Error::NotReachable(endpoint.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_source_reads_named_files() {
+        let directory = std::env::temp_dir().join(format!("ybtop_file_source_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let endpoint = Endpoint::new("192.168.66.80", "13000");
+        fs::write(directory.join("192.168.66.80_13000_rpcz.json"), r#"{"connections": []}"#).unwrap();
+        let mut source = FileSource::new(&directory);
+        assert_eq!(source.fetch(&endpoint, "/rpcz").unwrap(), r#"{"connections": []}"#);
+        assert!(matches!(source.fetch(&endpoint, "/api/v1/version"), Err(Error::NotReachable(_))));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn http_source_port_not_listening() {
+        // a port that was just free refuses the connection.
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let endpoint = Endpoint::new("127.0.0.1", &port.to_string());
+        let result = HttpSource::new(Duration::from_secs(1)).fetch(&endpoint, "/rpcz");
+        assert!(matches!(result, Err(Error::NotReachable(_))), "{:?}", result);
+    }
+
+    #[test]
+    fn scripted_source_returns_responses_in_order() {
+        let endpoint = Endpoint::new("192.168.66.80", "12000");
+        let mut source = ScriptedSource::new();
+        source.push(&endpoint, "/rpcz", Ok(String::from("{}")));
+        source.push(&endpoint, "/rpcz", Err(Error::Timeout(endpoint.to_string())));
+        assert_eq!(source.fetch(&endpoint, "/rpcz").unwrap(), "{}");
+        assert!(matches!(source.fetch(&endpoint, "/rpcz"), Err(Error::Timeout(_))));
+        assert!(matches!(source.fetch(&endpoint, "/rpcz"), Err(Error::NotReachable(_))));
+    }
+}
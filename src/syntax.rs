@@ -0,0 +1,218 @@
+//! Syntax highlighting of SQL and CQL: a lightweight tokenizer that colors the keywords,
+//! identifiers, literals and bind markers of a query for the terminal, and the escaping of the control
+//! characters of text that is shown.
+use std::env;
+use std::io::{self, IsTerminal};
+
+/// Whether to color: when the output is a terminal, and NO_COLOR isn't set (see no-color.org).
+pub fn use_color() -> bool {
+    io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// The keywords of SQL and CQL that are colored, lowercase.
+const KEYWORDS: [&str; 84] = [
+    "all", "allow", "alter", "analyze", "and", "apply", "as", "asc", "batch", "begin", "between", "by", "case",
+    "cast", "commit", "copy", "create", "cross", "default", "delete", "desc", "distinct", "drop", "else", "end",
+    "except", "exists", "explain", "false", "filtering", "for", "from", "full", "grant", "group", "having", "if",
+    "ilike", "in", "index", "inner", "insert", "intersect", "into", "is", "join", "key", "keyspace", "left", "like",
+    "limit", "lock", "not", "null", "offset", "on", "or", "order", "outer", "primary", "returning", "revoke",
+    "right", "rollback", "select", "set", "start", "table", "then", "timestamp", "token", "transaction", "true",
+    "truncate", "ttl", "union", "update", "using", "vacuum", "values", "when", "where", "with", "writetime",
+];
+
+/// Colors of the tokens, as ANSI foreground colors.
+const KEYWORD_COLOR: &str = "\x1b[34m";
+const IDENTIFIER_COLOR: &str = "\x1b[36m";
+const LITERAL_COLOR: &str = "\x1b[32m";
+const BIND_MARKER_COLOR: &str = "\x1b[33m";
+const DEFAULT_COLOR: &str = "\x1b[39m";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Token {
+    Keyword,
+    /// a name, or a quoted name.
+    Identifier,
+    /// a string or a number.
+    Literal,
+    /// ?, $1 or :name.
+    BindMarker,
+}
+
+/// A part of a text, from start to end in bytes, that is shown between an on and an off escape sequence.
+pub type Span = (usize, usize, &'static str, &'static str);
+
+/// The tokens of a query, as their kind, start and end in bytes. Punctuation and operators are left out.
+pub fn tokens(query: &str) -> Vec<(Token, usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            '\'' | '"' => {
+                // a quote in a literal or quoted name is written as two quotes.
+                while let Some((_, next)) = chars.next() {
+                    if next == c {
+                        if chars.peek().is_some_and(|(_, after)| *after == c) {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                if c == '\'' {
+                    Token::Literal
+                } else {
+                    Token::Identifier
+                }
+            }
+            '?' => Token::BindMarker,
+            '$' if chars.peek().is_some_and(|(_, next)| next.is_ascii_digit()) => {
+                while chars.next_if(|(_, next)| next.is_ascii_digit()).is_some() {}
+                Token::BindMarker
+            }
+            // the :: of a cast.
+            ':' if chars.peek().is_some_and(|(_, next)| *next == ':') => {
+                chars.next();
+                continue;
+            }
+            ':' if chars.peek().is_some_and(|(_, next)| next.is_alphabetic() || *next == '_') => {
+                while chars.next_if(|(_, next)| next.is_alphanumeric() || *next == '_').is_some() {}
+                Token::BindMarker
+            }
+            c if c.is_ascii_digit() => {
+                while chars.next_if(|(_, next)| next.is_ascii_digit() || *next == '.').is_some() {}
+                Token::Literal
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while chars.next_if(|(_, next)| next.is_alphanumeric() || *next == '_' || *next == '$').is_some() {}
+                let end = chars.peek().map_or(query.len(), |(end, _)| *end);
+                if KEYWORDS.binary_search(&query[start..end].to_lowercase().as_str()).is_ok() {
+                    Token::Keyword
+                } else {
+                    Token::Identifier
+                }
+            }
+            _ => continue,
+        };
+        let end = chars.peek().map_or(query.len(), |(end, _)| *end);
+        tokens.push((token, start, end));
+    }
+    tokens
+}
+
+/// The spans that color the tokens of a query.
+pub fn spans(query: &str) -> Vec<Span> {
+    tokens(query)
+        .into_iter()
+        .map(|(token, start, end)| {
+            let color = match token {
+                Token::Keyword => KEYWORD_COLOR,
+                Token::Identifier => IDENTIFIER_COLOR,
+                Token::Literal => LITERAL_COLOR,
+                Token::BindMarker => BIND_MARKER_COLOR,
+            };
+            (start, end, color, DEFAULT_COLOR)
+        })
+        .collect()
+}
+
+/// The text with the escape sequences of the spans. Spans of different kinds, such as colors and
+/// the reverse video of search matches, can overlap.
+pub fn paint(text: &str, spans: &[Span]) -> String {
+    // the ends go before the starts at the same position.
+    let mut codes: Vec<(usize, bool, &str)> = Vec::new();
+    for (start, end, on, off) in spans {
+        codes.push((*start, true, on));
+        codes.push((*end, false, off));
+    }
+    codes.sort_by_key(|(position, starts, _)| (*position, *starts));
+    let mut painted = String::with_capacity(text.len() + codes.len() * 5);
+    let mut position = 0;
+    for (at, _, code) in codes {
+        painted.push_str(&text[position..at]);
+        painted.push_str(code);
+        position = at;
+    }
+    painted.push_str(&text[position..]);
+    painted
+}
+
+/// The number of characters of a text that are shown, leaving out its escape sequences.
+pub fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // up to the final letter of the sequence.
+            chars.find(char::is_ascii_alphabetic);
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// The query colored.
+pub fn colorize(query: &str) -> String {
+    paint(query, &spans(query))
+}
+
+/// The text with its control characters escaped, so text from the cluster, such as a query, can't
+/// move the cursor or change the colors of the terminal.
+pub fn printable(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { c.escape_default().to_string() } else { c.to_string() })
+        .collect()
+}
+
+/// The text on one line, with its runs of whitespace as one space and the other control characters
+/// escaped.
+pub fn one_line(text: &str) -> String {
+    printable(&text.split_whitespace().collect::<Vec<&str>>().join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_control_characters() {
+        assert_eq!(printable("a\x1b[2J\tb\u{3b1}"), "a\\u{1b}[2J\\tb\u{3b1}");
+        assert_eq!(one_line("select 1\r\n  from t\twhere a = '\x07'"), "select 1 from t where a = '\\u{7}'");
+    }
+
+    #[test]
+    fn tokenize_query() {
+        let query = "INSERT INTO cr.\"Checks\" (state, n) VALUES ('O''Brien', 4.5, ?, $1, :id) -- x::text";
+        let tokens: Vec<(Token, &str)> = tokens(query).into_iter().map(|(token, start, end)| (token, &query[start..end])).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::Keyword, "INSERT"),
+                (Token::Keyword, "INTO"),
+                (Token::Identifier, "cr"),
+                (Token::Identifier, "\"Checks\""),
+                (Token::Identifier, "state"),
+                (Token::Identifier, "n"),
+                (Token::Keyword, "VALUES"),
+                (Token::Literal, "'O''Brien'"),
+                (Token::Literal, "4.5"),
+                (Token::BindMarker, "?"),
+                (Token::BindMarker, "$1"),
+                (Token::BindMarker, ":id"),
+                (Token::Identifier, "x"),
+                (Token::Identifier, "text"),
+            ]
+        );
+        assert_eq!(colorize("select 1"), "\x1b[34mselect\x1b[39m \x1b[32m1\x1b[39m");
+        assert_eq!(
+            paint("select 1", &[(0, 6, "[", "]"), (3, 8, "<", ">")]),
+            "[sel<ect] 1>"
+        );
+        assert_eq!(visible_width(&colorize("select 1")), 8);
+    }
+
+    #[test]
+    fn keywords_are_sorted() {
+        assert!(KEYWORDS.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
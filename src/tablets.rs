@@ -0,0 +1,354 @@
+//! Tablet hotspots: the calls in flight on the tservers are mapped to their tablets and tables with
+//! the master's /dump-entities, and ranked per table, per tablet and per tablet leader by their elapsed time.
+use crate::snapshot::GeneralPresentation;
+use crate::syntax::{self, Token};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+/// The master's /dump-entities output: keyspaces, tables and tablets with their replicas.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Entities {
+    #[serde(default)]
+    pub keyspaces: Vec<Keyspace>,
+    #[serde(default)]
+    pub tables: Vec<Table>,
+    #[serde(default)]
+    pub tablets: Vec<Tablet>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Keyspace {
+    pub keyspace_id: String,
+    pub keyspace_name: String,
+    pub keyspace_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Table {
+    pub table_id: String,
+    pub keyspace_id: String,
+    pub table_name: String,
+    pub state: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Tablet {
+    pub table_id: String,
+    pub tablet_id: String,
+    pub state: Option<String>,
+    #[serde(default)]
+    pub replicas: Vec<Replica>,
+    pub leader: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Replica {
+    #[serde(rename = "type")]
+    pub replica_type: Option<String>,
+    pub server_uuid: String,
+    pub addr: String,
+}
+
+/// A call in flight on a tserver, as found in the tserver's /rpcz.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TserverCall {
+    pub elapsed_ms: u32,
+    pub tablet_id: Option<String>,
+}
+
+pub struct Hotspot {
+    pub name: String,
+    pub detail: String,
+    pub calls: usize,
+    pub elapsed_ms: u64,
+    pub max_elapsed_ms: u32,
+}
+
+pub fn parse_entities(http_data: String) -> Option<Entities> {
+    serde_json::from_str(&http_data).ok()
+}
+
+/// Tablet peers prefix their trace lines with "T <tablet_id> P <peer_id>: ",
+/// which is the only place a call in the tserver /rpcz tells which tablet it is for.
+pub fn tablet_id_from_trace(trace: &str) -> Option<String> {
+    for (position, _) in trace.match_indices("T ") {
+        // the trace can have any text after a "T ", such as a quoted literal, so it is cut by bytes.
+        let candidate = &trace.as_bytes()[position + 2..];
+        if let (Some(id), Some(b" P ")) = (candidate.get(..32), candidate.get(32..35)) {
+            if id.iter().all(u8::is_ascii_hexdigit) {
+                return Some(String::from_utf8_lossy(id).into_owned());
+            }
+        }
+    }
+    None
+}
+
+impl Entities {
+    /// The table as keyspace.table.
+    pub fn table_name(&self, table_id: &str) -> Option<String> {
+        let table = self.tables.iter().find(|t| t.table_id == table_id)?;
+        let keyspace_name = self
+            .keyspaces
+            .iter()
+            .find(|k| k.keyspace_id == table.keyspace_id)
+            .map(|k| k.keyspace_name.as_str())
+            .unwrap_or("?");
+        Some(format!("{}.{}", keyspace_name, table.table_name))
+    }
+
+    pub fn tablet(&self, tablet_id: &str) -> Option<&Tablet> {
+        self.tablets.iter().find(|t| t.tablet_id == tablet_id)
+    }
+}
+
+impl Tablet {
+    pub fn leader_addr(&self) -> String {
+        match &self.leader {
+            Some(leader) => self
+                .replicas
+                .iter()
+                .find(|r| &r.server_uuid == leader)
+                .map(|r| r.addr.clone())
+                .unwrap_or_else(|| leader.clone()),
+            None => String::from("no leader"),
+        }
+    }
+}
+
+fn add_call(hotspots: &mut HashMap<String, Hotspot>, name: String, detail: String, elapsed_ms: u32) {
+    let hotspot = hotspots.entry(name.clone()).or_insert(Hotspot {
+        name,
+        detail,
+        calls: 0,
+        elapsed_ms: 0,
+        max_elapsed_ms: 0,
+    });
+    hotspot.calls += 1;
+    hotspot.elapsed_ms += elapsed_ms as u64;
+    hotspot.max_elapsed_ms = hotspot.max_elapsed_ms.max(elapsed_ms);
+}
+
+fn ranked(hotspots: HashMap<String, Hotspot>) -> Vec<Hotspot> {
+    let mut hotspots: Vec<Hotspot> = hotspots.into_values().collect();
+    hotspots.sort_by(|a, b| b.elapsed_ms.cmp(&a.elapsed_ms).then(b.calls.cmp(&a.calls)));
+    hotspots
+}
+
+/// Aggregate the calls in flight per table, ranked by total elapsed time.
+pub fn hot_tables(calls: &[TserverCall], entities: &Entities) -> Vec<Hotspot> {
+    let mut hotspots: HashMap<String, Hotspot> = HashMap::new();
+    for call in calls {
+        let (name, detail) = match call.tablet_id.as_deref().and_then(|id| entities.tablet(id)) {
+            Some(tablet) => (
+                entities.table_name(&tablet.table_id).unwrap_or_else(|| String::from("(unknown table)")),
+                tablet.table_id.clone(),
+            ),
+            None if call.tablet_id.is_some() => (String::from("(unknown table)"), String::new()),
+            None => (String::from("(no tablet id)"), String::new()),
+        };
+        add_call(&mut hotspots, name, detail, call.elapsed_ms);
+    }
+    ranked(hotspots)
+}
+
+/// Aggregate the calls in flight per tablet, with its table and leader, ranked by total elapsed time.
+pub fn hot_tablets(calls: &[TserverCall], entities: &Entities) -> Vec<Hotspot> {
+    let mut hotspots: HashMap<String, Hotspot> = HashMap::new();
+    for call in calls {
+        if let Some(tablet_id) = &call.tablet_id {
+            let detail = match entities.tablet(tablet_id) {
+                Some(tablet) => format!(
+                    "{} leader: {}",
+                    entities.table_name(&tablet.table_id).unwrap_or_else(|| String::from("(unknown table)")),
+                    tablet.leader_addr()
+                ),
+                None => String::from("(unknown tablet)"),
+            };
+            add_call(&mut hotspots, tablet_id.clone(), detail, call.elapsed_ms);
+        }
+    }
+    ranked(hotspots)
+}
+
+/// Aggregate the calls in flight per tablet leader, with the number of its tablets they are for, ranked
+/// by total elapsed time: a leader node that serves more than its share of the calls is a hot node.
+pub fn hot_leaders(calls: &[TserverCall], entities: &Entities) -> Vec<Hotspot> {
+    let mut hotspots: HashMap<String, Hotspot> = HashMap::new();
+    let mut tablets: HashMap<String, HashSet<&str>> = HashMap::new();
+    for call in calls {
+        if let Some(tablet) = call.tablet_id.as_deref().and_then(|id| entities.tablet(id)) {
+            let leader = tablet.leader_addr();
+            tablets.entry(leader.clone()).or_default().insert(&tablet.tablet_id);
+            add_call(&mut hotspots, leader, String::new(), call.elapsed_ms);
+        }
+    }
+    for hotspot in hotspots.values_mut() {
+        hotspot.detail = format!("{} tablets", tablets[&hotspot.name].len());
+    }
+    ranked(hotspots)
+}
+
+/// The hot tables that a session's query or statements name, in the database or keyspace of the session.
+/// Names are compared ignoring case and quotes, so a table of the same name in another schema matches too.
+pub fn hot_tables_of<'a>(row: &GeneralPresentation, hot_tables: &'a [Hotspot]) -> Vec<&'a Hotspot> {
+    let mut names = HashSet::new();
+    for query in std::iter::once(&row.query).chain(&row.statements) {
+        for (token, start, end) in syntax::tokens(query) {
+            if token == Token::Identifier {
+                names.insert(query[start..end].trim_matches('"').to_lowercase());
+            }
+        }
+    }
+    hot_tables
+        .iter()
+        .filter(|hotspot| match hotspot.name.split_once('.') {
+            Some((keyspace, table)) => {
+                keyspace.eq_ignore_ascii_case(&row.keyspace_db_name) && names.contains(&table.to_lowercase())
+            }
+            None => false,
+        })
+        .collect()
+}
+
+pub fn write_hotspots(out: &mut dyn Write, calls: &[TserverCall], entities: &Entities) -> io::Result<()> {
+    writeln!(out)?;
+    writeln!(out, "{:40} {:>6} {:>10} {:>8} table_id", "hot tables", "calls", "elapsed_s", "max_s")?;
+    for row in hot_tables(calls, entities).iter().take(10) {
+        writeln!(
+            out,
+            "{:40} {:6} {:10.3} {:8.3} {}",
+            row.name,
+            row.calls,
+            row.elapsed_ms as f32 / 1000.0,
+            row.max_elapsed_ms as f32 / 1000.0,
+            row.detail
+        )?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:40} {:>6} {:>10} {:>8} table/leader", "hot tablets", "calls", "elapsed_s", "max_s")?;
+    for row in hot_tablets(calls, entities).iter().take(10) {
+        writeln!(
+            out,
+            "{:40} {:6} {:10.3} {:8.3} {}",
+            row.name,
+            row.calls,
+            row.elapsed_ms as f32 / 1000.0,
+            row.max_elapsed_ms as f32 / 1000.0,
+            row.detail
+        )?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:40} {:>6} {:>10} {:>8} tablets", "hot leaders", "calls", "elapsed_s", "max_s")?;
+    for row in hot_leaders(calls, entities).iter().take(10) {
+        writeln!(
+            out,
+            "{:40} {:6} {:10.3} {:8.3} {}",
+            row.name,
+            row.calls,
+            row.elapsed_ms as f32 / 1000.0,
+            row.max_elapsed_ms as f32 / 1000.0,
+            row.detail
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMP_ENTITIES: &str = r#"
+{
+    "keyspaces": [
+        {
+            "keyspace_id": "000033e1000030008000000000000000",
+            "keyspace_name": "yugabyte",
+            "keyspace_type": "ysql"
+        }
+    ],
+    "tables": [
+        {
+            "table_id": "000033e1000030008000000000004000",
+            "keyspace_id": "000033e1000030008000000000000000",
+            "table_name": "orders",
+            "state": "RUNNING"
+        }
+    ],
+    "tablets": [
+        {
+            "table_id": "000033e1000030008000000000004000",
+            "tablet_id": "7bb48b1e4a8c4bbb9e4ffa8e4c2a1e0d",
+            "state": "RUNNING",
+            "replicas": [
+                {
+                    "type": "VOTER",
+                    "server_uuid": "0a1b2c3d4e5f40718293a4b5c6d7e8f9",
+                    "addr": "192.168.66.80:9100"
+                },
+                {
+                    "type": "VOTER",
+                    "server_uuid": "1b2c3d4e5f40718293a4b5c6d7e8f90a",
+                    "addr": "192.168.66.81:9100"
+                }
+            ],
+            "leader": "0a1b2c3d4e5f40718293a4b5c6d7e8f9"
+        }
+    ]
+}
+    "#;
+
+    #[test]
+    fn parse_dump_entities() {
+        let entities = parse_entities(DUMP_ENTITIES.to_string()).unwrap();
+        assert_eq!(entities.table_name("000033e1000030008000000000004000").unwrap(), "yugabyte.orders");
+        assert_eq!(entities.tablet("7bb48b1e4a8c4bbb9e4ffa8e4c2a1e0d").unwrap().leader_addr(), "192.168.66.80:9100");
+    }
+
+    #[test]
+    fn tablet_id_from_trace_prefix() {
+        let trace = "0328 13:11:38.653701 (+    26us) tablet_service.cc:1940] T 7bb48b1e4a8c4bbb9e4ffa8e4c2a1e0d P 0a1b2c3d4e5f40718293a4b5c6d7e8f9: Start Read";
+        assert_eq!(tablet_id_from_trace(trace).as_deref(), Some("7bb48b1e4a8c4bbb9e4ffa8e4c2a1e0d"));
+        assert_eq!(tablet_id_from_trace("0328 13:11:38.653675 (+     0us) service_pool.cc:147] Inserting onto call queue"), None);
+        // a multibyte character where the tablet id would end.
+        assert_eq!(tablet_id_from_trace("T 'Zürich-Straße-Öffnungszeiten-Süd' and T 7bb4 P"), None);
+        assert_eq!(tablet_id_from_trace("T 7bb48b1e4a8c4bbb9e4ffa8e4c2a1e0dé"), None);
+    }
+
+    #[test]
+    fn rank_hot_tables_and_tablets() {
+        let entities = parse_entities(DUMP_ENTITIES.to_string()).unwrap();
+        let calls = vec![
+            TserverCall { elapsed_ms: 1500, tablet_id: Some(String::from("7bb48b1e4a8c4bbb9e4ffa8e4c2a1e0d")) },
+            TserverCall { elapsed_ms: 500, tablet_id: Some(String::from("7bb48b1e4a8c4bbb9e4ffa8e4c2a1e0d")) },
+            TserverCall { elapsed_ms: 100, tablet_id: None },
+        ];
+        let tables = hot_tables(&calls, &entities);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].name, "yugabyte.orders");
+        assert_eq!(tables[0].calls, 2);
+        assert_eq!(tables[0].elapsed_ms, 2000);
+        assert_eq!(tables[0].max_elapsed_ms, 1500);
+        assert_eq!(tables[1].name, "(no tablet id)");
+        let tablets = hot_tablets(&calls, &entities);
+        assert_eq!(tablets.len(), 1);
+        assert_eq!(tablets[0].detail, "yugabyte.orders leader: 192.168.66.80:9100");
+        let leaders = hot_leaders(&calls, &entities);
+        assert_eq!(leaders.len(), 1);
+        assert_eq!(leaders[0].name, "192.168.66.80:9100");
+        assert_eq!(leaders[0].calls, 2);
+        assert_eq!(leaders[0].elapsed_ms, 2000);
+        assert_eq!(leaders[0].detail, "1 tablets");
+
+        let row = |db: &str, query: &str| GeneralPresentation {
+            api: String::from("YSQL"),
+            keyspace_db_name: db.to_string(),
+            query: query.to_string(),
+            ..Default::default()
+        };
+        let names = |row: &GeneralPresentation| hot_tables_of(row, &tables).iter().map(|t| t.name.clone()).collect::<Vec<String>>();
+        assert_eq!(names(&row("yugabyte", "select * from public.\"Orders\" where id = $1")), vec!["yugabyte.orders"]);
+        assert!(names(&row("yugabyte", "select * from orders_archive")).is_empty());
+        assert!(names(&row("postgres", "select * from orders")).is_empty());
+    }
+}
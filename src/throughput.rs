@@ -0,0 +1,151 @@
+//! YCQL throughput: the calls per second of every client connection, from the difference of its
+//! processed_call_count between refreshes. Clients with many short calls rarely have a call in
+//! flight at a refresh, so these don't show up in the session list.
+use crate::rpcz::AllConnections::InboundConnections;
+use crate::snapshot::ClusterSnapshot;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone)]
+pub struct ConnectionThroughput {
+    pub server: String,
+    pub client: String,
+    pub keyspace: String,
+    /// the calls processed since the previous refresh.
+    pub calls: u64,
+    pub calls_per_second: f64,
+}
+
+#[derive(Default)]
+pub struct ThroughputTracker {
+    /// the processed call count per endpoint and client at the previous refresh.
+    counts: HashMap<(String, String), u32>,
+    previous: Option<SystemTime>,
+}
+
+impl ThroughputTracker {
+    pub fn new() -> Self {
+        ThroughputTracker::default()
+    }
+
+    /// The throughput of the YCQL connections that were also there at the previous refresh, the highest first.
+    /// A connection is YCQL when it has CQL connection details.
+    pub fn update(&mut self, snapshot: &ClusterSnapshot) -> Vec<ConnectionThroughput> {
+        let seconds = self
+            .previous
+            .and_then(|previous| snapshot.timestamp.duration_since(previous).ok())
+            .map(|elapsed| elapsed.as_secs_f64())
+            .filter(|seconds| *seconds > 0.0);
+        let mut counts = HashMap::new();
+        let mut throughput = Vec::new();
+        for endpoint_snapshot in &snapshot.endpoints {
+            let inbound_connections = match &endpoint_snapshot.connections {
+                Some(InboundConnections { inbound_connections }) => inbound_connections,
+                _ => continue,
+            };
+            for connection in inbound_connections {
+                let details = match &connection.connection_details {
+                    Some(details) => details,
+                    None => continue,
+                };
+                let key = (endpoint_snapshot.endpoint.to_string(), connection.remote_ip.clone());
+                // a count lower than before is a new connection from the same address and port.
+                if let (Some(previous), Some(seconds)) = (self.counts.get(&key), seconds) {
+                    if connection.processed_call_count >= *previous {
+                        let calls = (connection.processed_call_count - previous) as u64;
+                        throughput.push(ConnectionThroughput {
+                            server: endpoint_snapshot.endpoint.hostname.clone(),
+                            client: connection.remote_ip.clone(),
+                            keyspace: details.cql_connection_details.keyspace.clone(),
+                            calls,
+                            calls_per_second: calls as f64 / seconds,
+                        });
+                    }
+                }
+                counts.insert(key, connection.processed_call_count);
+            }
+        }
+        throughput.sort_by(|a, b| b.calls.cmp(&a.calls).then(a.client.cmp(&b.client)));
+        self.counts = counts;
+        self.previous = Some(snapshot.timestamp);
+        throughput
+    }
+}
+
+/// The calls per second summed by a field of the connections, the highest first.
+pub fn calls_per_second_by(throughput: &[ConnectionThroughput], field: fn(&ConnectionThroughput) -> &str) -> Vec<(String, f64)> {
+    let mut totals: BTreeMap<String, f64> = BTreeMap::new();
+    for connection in throughput {
+        *totals.entry(field(connection).to_string()).or_default() += connection.calls_per_second;
+    }
+    let mut totals: Vec<(String, f64)> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.total_cmp(&a.1));
+    totals
+}
+
+/// Write the YCQL clients ranked by calls per second, and the totals per keyspace and per server.
+pub fn write_throughput(out: &mut dyn Write, throughput: &[ConnectionThroughput]) -> io::Result<()> {
+    writeln!(out, "{:20} {:20} {:10} {:>9} {:>8}", "server", "client", "keyspace", "calls/s", "calls")?;
+    for connection in throughput {
+        writeln!(
+            out,
+            "{:20} {:20} {:10} {:9.1} {:8}",
+            connection.server, connection.client, connection.keyspace, connection.calls_per_second, connection.calls
+        )?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:20} {:>9}", "keyspace", "calls/s")?;
+    for (keyspace, calls_per_second) in calls_per_second_by(throughput, |c| &c.keyspace) {
+        writeln!(out, "{:20} {:9.1}", keyspace, calls_per_second)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:20} {:>9}", "server", "calls/s")?;
+    for (server, calls_per_second) in calls_per_second_by(throughput, |c| &c.server) {
+        writeln!(out, "{:20} {:9.1}", server, calls_per_second)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::fixtures;
+
+    fn snapshot(seconds: u64, counts: &[(&str, &str, u32)]) -> ClusterSnapshot {
+        let connections: Vec<String> = counts
+            .iter()
+            .map(|(remote_ip, keyspace, count)| {
+                format!(
+                    r#"{{"remote_ip": "{}", "state": "OPEN", "processed_call_count": {}, "connection_details": {{"cql_connection_details": {{"keyspace": "{}"}}}}}}"#,
+                    remote_ip, count, keyspace
+                )
+            })
+            .collect();
+        fixtures::snapshot(seconds, &[("192.168.66.80:12000", &fixtures::ycql(&connections))])
+    }
+
+    #[test]
+    fn calls_per_second_from_processed_call_count() {
+        let mut tracker = ThroughputTracker::new();
+        assert!(tracker
+            .update(&snapshot(0, &[("10.0.0.5:40000", "cr", 100), ("10.0.0.6:40000", "cr", 5)]))
+            .is_empty());
+        let throughput = tracker.update(&snapshot(
+            2,
+            &[("10.0.0.5:40000", "cr", 700), ("10.0.0.6:40000", "cr", 9), ("10.0.0.7:40000", "ks", 50)],
+        ));
+        assert_eq!(throughput.len(), 2);
+        assert_eq!(throughput[0].client, "10.0.0.5:40000");
+        assert_eq!(throughput[0].calls, 600);
+        assert_eq!(throughput[0].calls_per_second, 300.0);
+        assert_eq!(throughput[1].calls_per_second, 2.0);
+        assert_eq!(calls_per_second_by(&throughput, |c| &c.keyspace), vec![(String::from("cr"), 302.0)]);
+
+        // a reconnect from the same address and port starts counting again.
+        let throughput = tracker.update(&snapshot(4, &[("10.0.0.5:40000", "cr", 3), ("10.0.0.7:40000", "ks", 60)]));
+        assert_eq!(throughput.len(), 1);
+        assert_eq!(throughput[0].client, "10.0.0.7:40000");
+        assert_eq!(throughput[0].calls_per_second, 5.0);
+    }
+}
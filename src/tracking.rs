@@ -0,0 +1,247 @@
+//! Following sessions over refreshes, to tell a new session from the next statement of a known one,
+//! and to count the statements of every session.
+use crate::snapshot::{ClusterSnapshot, GeneralPresentation};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+/// Identifies a row over refreshes: its session and call.
+pub type RowKey = (String, String);
+
+pub fn row_key(row: &GeneralPresentation) -> RowKey {
+    (row.session.clone(), row.call.clone())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RowState {
+    /// the session wasn't seen in the previous refresh.
+    New,
+    /// the session was seen, running another statement or idle.
+    NextStatement,
+    /// the same statement is still running, or there is no previous refresh to tell.
+    Continuing,
+    /// the row was shown in the previous refresh, and is gone now.
+    Finished,
+}
+
+impl RowState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RowState::New => "new",
+            RowState::NextStatement => "next",
+            RowState::Continuing => "",
+            RowState::Finished => "done",
+        }
+    }
+}
+
+/// A row of the session list with what the tracker knows of its session.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrackedRow {
+    pub row: GeneralPresentation,
+    pub state: RowState,
+    /// the statements that started per second since the session was first seen, once it was seen twice.
+    pub statements_per_second: Option<f64>,
+}
+
+struct TrackedSession {
+    /// the endpoint the session was read from.
+    endpoint: String,
+    first_seen: SystemTime,
+    /// the number of different statements seen.
+    statements: u64,
+    /// the statements seen in the last refresh.
+    calls: HashSet<String>,
+}
+
+#[derive(Default)]
+pub struct SessionTracker {
+    sessions: HashMap<String, TrackedSession>,
+    /// the rows shown in the previous refresh with their endpoint, by session and call.
+    rows: HashMap<RowKey, (String, GeneralPresentation)>,
+    previous: Option<SystemTime>,
+}
+
+impl SessionTracker {
+    pub fn new() -> Self {
+        SessionTracker::default()
+    }
+
+    /// Compare a snapshot with the previous one, and return its rows, followed by the rows that finished:
+    /// the rows that are gone, and the rows of which the status changed, as they were.
+    /// All sessions are followed, also the idle ones, so a session that was idle in between is not new.
+    /// The sessions and rows of an endpoint that couldn't be read are kept as they were until it can be
+    /// read again, as they may still be running.
+    pub fn update(&mut self, snapshot: &ClusterSnapshot, idle: bool) -> Vec<TrackedRow> {
+        let unreadable: HashSet<String> =
+            snapshot.endpoints.iter().filter(|e| e.error.is_some()).map(|e| e.endpoint.to_string()).collect();
+        let mut sessions: HashMap<String, TrackedSession> = HashMap::new();
+        for (endpoint, row) in endpoint_sessions(snapshot, true) {
+            let previous = self.sessions.get(&row.session);
+            let session = sessions.entry(row.session.clone()).or_insert_with(|| TrackedSession {
+                endpoint,
+                first_seen: previous.map_or(snapshot.timestamp, |p| p.first_seen),
+                statements: previous.map_or(0, |p| p.statements),
+                calls: HashSet::new(),
+            });
+            if !previous.is_some_and(|p| p.calls.contains(&row.call)) && !session.calls.contains(&row.call) {
+                session.statements += 1;
+            }
+            session.calls.insert(row.call);
+        }
+        let mut tracked = Vec::new();
+        let mut rows = HashMap::new();
+        for (endpoint, row) in endpoint_sessions(snapshot, idle) {
+            let key = row_key(&row);
+            let state = if self.previous.is_none() || self.rows.contains_key(&key) {
+                RowState::Continuing
+            } else if self.sessions.contains_key(&row.session) {
+                RowState::NextStatement
+            } else {
+                RowState::New
+            };
+            tracked.push(TrackedRow {
+                statements_per_second: statements_per_second(sessions.get(&row.session), snapshot.timestamp),
+                row: row.clone(),
+                state,
+            });
+            rows.insert(key, (endpoint, row));
+        }
+        for (id, session) in self.sessions.drain() {
+            if unreadable.contains(&session.endpoint) {
+                sessions.entry(id).or_insert(session);
+            }
+        }
+        // a row that is gone has finished, and so has one of which the status changed, such as a YSQL
+        // query that finished and left its session idle, which keeps its query start time.
+        let mut finished: Vec<GeneralPresentation> = Vec::new();
+        for (key, (endpoint, row)) in self.rows.drain() {
+            if unreadable.contains(&endpoint) {
+                rows.entry(key).or_insert((endpoint, row));
+            } else if rows.get(&key).is_none_or(|(_, now)| now.status != row.status) {
+                finished.push(row);
+            }
+        }
+        finished.sort_by(|a, b| b.query_time_ms.cmp(&a.query_time_ms).then(a.client.cmp(&b.client)));
+        for row in finished {
+            tracked.push(TrackedRow {
+                statements_per_second: statements_per_second(sessions.get(&row.session), snapshot.timestamp),
+                row,
+                state: RowState::Finished,
+            });
+        }
+        self.sessions = sessions;
+        self.rows = rows;
+        self.previous = Some(snapshot.timestamp);
+        tracked
+    }
+}
+
+/// The sessions of a snapshot with their endpoint, in the order of ClusterSnapshot::sessions.
+fn endpoint_sessions(snapshot: &ClusterSnapshot, idle: bool) -> Vec<(String, GeneralPresentation)> {
+    let mut rows: Vec<(String, GeneralPresentation)> = snapshot
+        .endpoints
+        .iter()
+        .flat_map(|e| {
+            let endpoint = e.endpoint.to_string();
+            e.sessions(idle).into_iter().map(move |row| (endpoint.clone(), row))
+        })
+        .collect();
+    rows.sort_by_key(|(_, row)| row.query_time_ms);
+    rows.reverse();
+    rows
+}
+
+fn statements_per_second(session: Option<&TrackedSession>, now: SystemTime) -> Option<f64> {
+    let session = session?;
+    let seconds = now.duration_since(session.first_seen).ok()?.as_secs_f64();
+    if seconds > 0.0 {
+        // the statement the session was running when it was first seen started before.
+        Some((session.statements - 1) as f64 / seconds)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::fixtures;
+
+    fn snapshot(seconds: u64, connections: &[(&str, &str, &str)]) -> ClusterSnapshot {
+        let connections: Vec<String> = connections
+            .iter()
+            .map(|(port, status, query_start_time)| {
+                format!(
+                    r#"{{"process_start_time": "2022-03-27 15:55:22.587029+00", "backend_type": "client backend", "backend_status": "{}", "db_name": "yugabyte", "host": "127.0.0.1", "port": "{}", "query": "select 1", "query_start_time": "{}", "query_running_for_ms": 10}}"#,
+                    status, port, query_start_time
+                )
+            })
+            .collect();
+        fixtures::snapshot(seconds, &[("192.168.66.80:13000", &fixtures::ysql(&connections))])
+    }
+
+    fn states(rows: &[TrackedRow]) -> Vec<(&str, RowState)> {
+        rows.iter().map(|r| (r.row.client.as_str(), r.state)).collect()
+    }
+
+    #[test]
+    fn track_sessions_over_refreshes() {
+        let mut tracker = SessionTracker::new();
+        let rows = tracker.update(&snapshot(0, &[("50736", "active", "t1"), ("50737", "active", "t1")]), false);
+        assert_eq!(states(&rows), vec![("127.0.0.1:50737", RowState::Continuing), ("127.0.0.1:50736", RowState::Continuing)]);
+
+        // 50736 runs the same statement, 50737 goes idle, 50738 connects.
+        let rows = tracker.update(
+            &snapshot(3, &[("50736", "active", "t1"), ("50737", "idle", "t1"), ("50738", "active", "t1")]),
+            false,
+        );
+        assert_eq!(
+            states(&rows),
+            vec![
+                ("127.0.0.1:50738", RowState::New),
+                ("127.0.0.1:50736", RowState::Continuing),
+                ("127.0.0.1:50737", RowState::Finished)
+            ]
+        );
+        assert_eq!(rows[1].statements_per_second, Some(0.0));
+
+        // 50737 runs its next statement.
+        let rows = tracker.update(&snapshot(6, &[("50737", "active", "t2")]), false);
+        assert_eq!(
+            states(&rows),
+            vec![
+                ("127.0.0.1:50737", RowState::NextStatement),
+                ("127.0.0.1:50736", RowState::Finished),
+                ("127.0.0.1:50738", RowState::Finished)
+            ]
+        );
+        assert_eq!(rows[0].statements_per_second, Some(1.0 / 6.0));
+    }
+
+    #[test]
+    fn finish_query_that_turns_idle() {
+        let mut tracker = SessionTracker::new();
+        tracker.update(&snapshot(0, &[("50736", "active", "t1")]), true);
+        let rows = tracker.update(&snapshot(3, &[("50736", "idle", "t1")]), true);
+        assert_eq!(states(&rows), vec![("127.0.0.1:50736", RowState::Continuing), ("127.0.0.1:50736", RowState::Finished)]);
+        assert_eq!(rows[1].row.status, "active");
+        // and it finished once.
+        let rows = tracker.update(&snapshot(6, &[("50736", "idle", "t1")]), true);
+        assert_eq!(states(&rows), vec![("127.0.0.1:50736", RowState::Continuing)]);
+    }
+
+    #[test]
+    fn keep_sessions_of_an_unreadable_endpoint() {
+        let mut tracker = SessionTracker::new();
+        tracker.update(&snapshot(0, &[("50736", "active", "t1")]), false);
+        tracker.update(&snapshot(3, &[("50736", "active", "t1")]), false);
+
+        assert!(tracker.update(&fixtures::timed_out(6, "192.168.66.80:13000"), false).is_empty());
+
+        // the statement is still running, and the session keeps its first seen time and statements.
+        let rows = tracker.update(&snapshot(9, &[("50736", "active", "t1")]), false);
+        assert_eq!(states(&rows), vec![("127.0.0.1:50736", RowState::Continuing)]);
+        assert_eq!(rows[0].statements_per_second, Some(0.0));
+    }
+}
@@ -0,0 +1,299 @@
+//! Acting on YSQL sessions over the PostgreSQL protocol: connecting to the YSQL port of the server
+//! a session runs on, finding its backend in pg_stat_activity, and cancelling or terminating it,
+//! or explaining its statement, and keeping a connection to every node to read it at every refresh.
+use crate::error::Error;
+use crate::snapshot::GeneralPresentation;
+use crate::syntax::{self, Token};
+use postgres::{Client, Config, NoTls};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How to log in to the YSQL port of a server.
+#[derive(Debug, Clone)]
+pub struct Login {
+    pub port: u16,
+    pub user: String,
+    pub password: Option<String>,
+}
+
+impl Login {
+    pub fn connect(&self, host: &str, database: &str) -> Result<Client, Error> {
+        let mut config = Config::new();
+        config
+            .host(host)
+            .port(self.port)
+            .user(&self.user)
+            .dbname(database)
+            .application_name("ybtop")
+            .connect_timeout(Duration::from_secs(5));
+        if let Some(password) = &self.password {
+            config.password(password);
+        }
+        Ok(config.connect(NoTls)?)
+    }
+}
+
+/// Keeps a connection to a database on every node, to read it at every refresh.
+pub struct ClientPool {
+    login: Login,
+    database: String,
+    clients: BTreeMap<String, Client>,
+}
+
+impl ClientPool {
+    pub fn new(login: Login, database: &str) -> Self {
+        ClientPool {
+            login,
+            database: database.to_string(),
+            clients: BTreeMap::new(),
+        }
+    }
+
+    /// Read a server with its connection, made when there is none. A connection that fails is
+    /// dropped, and made again at the next refresh.
+    pub fn read<T>(&mut self, server: &str, read: impl FnOnce(&mut Client) -> Result<T, Error>) -> Result<T, Error> {
+        let client = match self.clients.get_mut(server) {
+            Some(client) => client,
+            None => {
+                let client = self.login.connect(server, &self.database)?;
+                self.clients.entry(server.to_string()).or_insert(client)
+            }
+        };
+        let result = read(client);
+        if result.is_err() {
+            self.clients.remove(server);
+        }
+        result
+    }
+}
+
+/// The login to a local PostgreSQL that stands in for a YSQL server, from YBTOP_TEST_POSTGRES as port and user.
+#[cfg(test)]
+pub(crate) fn test_login() -> Login {
+    let setting = std::env::var("YBTOP_TEST_POSTGRES").expect("set YBTOP_TEST_POSTGRES to port:user");
+    let (port, user) = setting.split_once(':').unwrap();
+    Login {
+        port: port.parse().unwrap(),
+        user: user.to_string(),
+        password: std::env::var("PGPASSWORD").ok(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    /// cancel the query that runs, with pg_cancel_backend.
+    Cancel,
+    /// end the session, with pg_terminate_backend.
+    Terminate,
+}
+
+impl Signal {
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Signal::Cancel => "cancel",
+            Signal::Terminate => "terminate",
+        }
+    }
+
+    fn function(&self) -> &'static str {
+        match self {
+            Signal::Cancel => "pg_cancel_backend",
+            Signal::Terminate => "pg_terminate_backend",
+        }
+    }
+}
+
+/// The database of a YSQL row, or yugabyte when /rpcz didn't tell.
+pub fn database(row: &GeneralPresentation) -> String {
+    if row.keyspace_db_name.is_empty() {
+        String::from("yugabyte")
+    } else {
+        row.keyspace_db_name.clone()
+    }
+}
+
+/// The backend of a YSQL session: the server and database, the client address and port, and when the query started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backend {
+    pub server: String,
+    pub database: String,
+    pub client_addr: String,
+    pub client_port: i32,
+    pub query_start: String,
+}
+
+impl Backend {
+    /// The backend of a row, with the query start time from the /rpcz fields of its session.
+    pub fn from_row(row: &GeneralPresentation, raw: Option<&Value>) -> Result<Self, String> {
+        if row.api != "YSQL" {
+            return Err(format!("{} sessions have no backend, only YSQL sessions do", row.api));
+        }
+        let (client_addr, client_port) = row
+            .client
+            .rsplit_once(':')
+            .and_then(|(addr, port)| Some((addr.to_string(), port.parse::<i32>().ok()?)))
+            .ok_or_else(|| format!("no client address and port in {}", row.client))?;
+        let query_start = raw
+            .and_then(|raw| raw.get("query_start_time"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("the session of {} on {} is no longer running a query", row.client, row.server))?;
+        Ok(Backend {
+            server: row.server.clone(),
+            database: database(row),
+            client_addr,
+            client_port,
+            query_start: query_start.to_string(),
+        })
+    }
+
+    /// The pid of the backend, when it still runs the same query.
+    pub fn find_pid(&self, client: &mut Client) -> Result<Option<i32>, Error> {
+        let rows = client.query(
+            "select pid from pg_stat_activity where client_addr = $1::text::inet and client_port = $2 and query_start = $3::text::timestamptz",
+            &[&self.client_addr, &self.client_port, &self.query_start],
+        )?;
+        Ok(rows.first().map(|row| row.get(0)))
+    }
+}
+
+/// Cancel or terminate a backend, and tell what happened.
+pub fn signal(login: &Login, backend: &Backend, signal: Signal) -> Result<String, Error> {
+    let mut client = login.connect(&backend.server, &backend.database)?;
+    let pid = match backend.find_pid(&mut client)? {
+        Some(pid) => pid,
+        None => {
+            return Ok(format!(
+                "the query of {}:{} on {} has finished",
+                backend.client_addr, backend.client_port, backend.server
+            ))
+        }
+    };
+    let signalled: bool = client.query_one(&format!("select {}($1)", signal.function()), &[&pid])?.get(0);
+    Ok(match (signalled, signal) {
+        (true, Signal::Cancel) => format!("cancelled the query of backend {} on {}", pid, backend.server),
+        (true, Signal::Terminate) => format!("terminated backend {} on {}", pid, backend.server),
+        (false, _) => format!("could not {} backend {} on {}", signal.verb(), pid, backend.server),
+    })
+}
+
+/// The first bind parameter of a query, such as $1: /rpcz shows the statement of a prepared statement
+/// without the values, and EXPLAIN can't plan it without them.
+pub fn bind_parameter(query: &str) -> Option<&str> {
+    syntax::tokens(query)
+        .into_iter()
+        .filter(|(token, _, _)| *token == Token::BindMarker)
+        .map(|(_, start, end)| &query[start..end])
+        .find(|marker| marker.starts_with('$'))
+}
+
+/// The plan of a query in a database on a server, with EXPLAIN, or with analyze, EXPLAIN ANALYZE,
+/// which runs the query. The query is run in a transaction that is rolled back.
+pub fn explain(login: &Login, server: &str, database: &str, query: &str, analyze: bool) -> Result<Vec<String>, Error> {
+    let mut client = login.connect(server, database)?;
+    let mut transaction = client.transaction()?;
+    let explain = if analyze { "explain analyze" } else { "explain" };
+    // a prepared statement is a single statement, so nothing after a ; runs.
+    let rows = transaction.query(&format!("{} {}", explain, query.trim().trim_end_matches(';')), &[])?;
+    transaction.rollback()?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::thread;
+
+    fn row(api: &str, client: &str) -> GeneralPresentation {
+        GeneralPresentation {
+            api: api.to_string(),
+            server: String::from("127.0.0.1"),
+            client: client.to_string(),
+            keyspace_db_name: String::from("postgres"),
+            status: String::from("active"),
+            query_time_ms: 1000,
+            query: String::from("select pg_sleep(60)"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn bind_parameters() {
+        assert_eq!(bind_parameter("select * from t where id = $1 and a = $2"), Some("$1"));
+        assert_eq!(bind_parameter("select * from t where id = '$1' and price = 10"), None);
+        assert_eq!(bind_parameter("select '{\"a\": 1}'::jsonb ? 'a'"), None);
+    }
+
+    #[test]
+    fn backend_of_row() {
+        let raw = json!({"query_start_time": "2022-03-27 15:55:22.587029+00"});
+        assert_eq!(
+            Backend::from_row(&row("YSQL", "10.0.0.5:50736"), Some(&raw)).unwrap(),
+            Backend {
+                server: String::from("127.0.0.1"),
+                database: String::from("postgres"),
+                client_addr: String::from("10.0.0.5"),
+                client_port: 50736,
+                query_start: String::from("2022-03-27 15:55:22.587029+00"),
+            }
+        );
+        assert!(Backend::from_row(&row("YCQL", "10.0.0.5:50736"), Some(&raw)).is_err());
+        assert!(Backend::from_row(&row("YSQL", "10.0.0.5:50736"), None).is_err());
+        assert!(Backend::from_row(&row("YSQL", ":"), Some(&raw)).is_err());
+    }
+
+    #[test]
+    #[ignore = "needs a local PostgreSQL, see ysql::test_login"]
+    fn cancel_local_backend() {
+        let login = test_login();
+        let mut sleeper = login.connect("127.0.0.1", "postgres").unwrap();
+        let sleeping = thread::spawn(move || sleeper.simple_query("select pg_sleep(60)"));
+        let mut client = login.connect("127.0.0.1", "postgres").unwrap();
+        // wait for the sleep to show up, as the /rpcz would show it.
+        let backend = (0..50)
+            .find_map(|_| {
+                thread::sleep(Duration::from_millis(100));
+                client
+                    .query_opt(
+                        "select host(client_addr), client_port, query_start::text from pg_stat_activity where query = 'select pg_sleep(60)'",
+                        &[],
+                    )
+                    .unwrap()
+            })
+            .map(|row| Backend {
+                server: String::from("127.0.0.1"),
+                database: String::from("postgres"),
+                client_addr: row.get(0),
+                client_port: row.get(1),
+                query_start: row.get(2),
+            })
+            .unwrap();
+        let message = signal(&login, &backend, Signal::Cancel).unwrap();
+        assert!(message.starts_with("cancelled the query of backend"), "{}", message);
+        assert!(sleeping.join().unwrap().is_err());
+        assert!(signal(&login, &backend, Signal::Cancel).unwrap().ends_with("has finished"));
+    }
+
+    #[test]
+    #[ignore = "needs a local PostgreSQL, see ysql::test_login"]
+    fn reconnect_after_error() {
+        let mut clients = ClientPool::new(test_login(), "postgres");
+        let pid = |client: &mut Client| -> Result<i32, Error> { Ok(client.query_one("select pg_backend_pid()", &[])?.get(0)) };
+        let first = clients.read("127.0.0.1", pid).unwrap();
+        assert_eq!(clients.read("127.0.0.1", pid).unwrap(), first);
+        assert!(clients.read("127.0.0.1", |client| Ok(client.batch_execute("select nonsense")?)).is_err());
+        assert_ne!(clients.read("127.0.0.1", pid).unwrap(), first);
+    }
+
+    #[test]
+    #[ignore = "needs a local PostgreSQL, see ysql::test_login"]
+    fn explain_local_query() {
+        let login = test_login();
+        let plan = explain(&login, "127.0.0.1", "postgres", "select * from pg_class where relname = 'pg_proc';", false).unwrap();
+        assert!(plan[0].contains("Scan"), "{:?}", plan);
+        let plan = explain(&login, "127.0.0.1", "postgres", "select count(*) from pg_class", true).unwrap();
+        assert!(plan.iter().any(|line| line.starts_with("Execution Time")), "{:?}", plan);
+        assert!(explain(&login, "127.0.0.1", "postgres", "select 1; select 2", false).is_err());
+    }
+}